@@ -0,0 +1,281 @@
+use crate::match_state::Team;
+use crate::parser::parser::{ActionType, Rally, Zone};
+use crate::stats::heatmap::Heatmap;
+use crate::stats::Percentage;
+
+// The court is drawn at 100 units per meter, the away team on the top half
+// and the home team on the bottom half with the net across the middle.
+const WIDTH: i32 = 900;
+const LENGTH: i32 = 1800;
+const NET: i32 = LENGTH / 2;
+const ZONE: i32 = 300;
+const SUB_ZONE: i32 = ZONE / 2;
+const MARGIN: i32 = 100;
+
+/// Row and column of a zone seen by the team standing in it and facing the
+/// net, row 0 is the front row and column 0 the left side.
+fn zone_cell(number: u8) -> (i32, i32) {
+    match number {
+        4 => (0, 0),
+        3 => (0, 1),
+        2 => (0, 2),
+        7 => (1, 0),
+        8 => (1, 1),
+        9 => (1, 2),
+        5 => (2, 0),
+        6 => (2, 1),
+        _ => (2, 2),
+    }
+}
+
+/// Row and column of a subzone within its zone, in the same perspective as
+/// `zone_cell`. A and B are on the net side, A on the left, C and D behind
+/// them with C on the right.
+fn sub_zone_cell(index: usize) -> (i32, i32) {
+    match index {
+        0 => (0, 0),
+        1 => (0, 1),
+        2 => (1, 1),
+        _ => (1, 0),
+    }
+}
+
+/// Top left corner of zone `number` on the side of `team`.
+fn zone_origin(team: Team, number: u8) -> (i32, i32) {
+    let (row, col) = zone_cell(number);
+
+    match team {
+        Team::Home => (col * ZONE, NET + row * ZONE),
+        Team::Away => ((2 - col) * ZONE, NET - (row + 1) * ZONE),
+    }
+}
+
+fn sub_zone_origin(team: Team, number: u8, index: usize) -> (i32, i32) {
+    let (x, y) = zone_origin(team, number);
+    let (row, col) = sub_zone_cell(index);
+
+    match team {
+        Team::Home => (x + col * SUB_ZONE, y + row * SUB_ZONE),
+        Team::Away => (x + (1 - col) * SUB_ZONE, y + (1 - row) * SUB_ZONE),
+    }
+}
+
+/// Center of `zone` on the side of `team`, `None` for zones off the court.
+fn zone_center(team: Team, zone: Zone) -> Option<(i32, i32)> {
+    let number = zone.number()?;
+
+    Some(match zone.sub_zone() {
+        Some(sub_zone) => {
+            let (x, y) = sub_zone_origin(team, number, sub_zone.index());
+            (x + SUB_ZONE / 2, y + SUB_ZONE / 2)
+        }
+        None => {
+            let (x, y) = zone_origin(team, number);
+            (x + ZONE / 2, y + ZONE / 2)
+        }
+    })
+}
+
+fn svg(content: String) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">{content}</svg>"#,
+        -MARGIN,
+        -MARGIN,
+        WIDTH + 2 * MARGIN,
+        LENGTH + 2 * MARGIN
+    )
+}
+
+/// The court lines with every zone and subzone outlined and labelled.
+fn court_lines() -> String {
+    let zones: String = [Team::Away, Team::Home]
+        .iter()
+        .flat_map(|team| (1..=9).map(move |number| (*team, number)))
+        .map(|(team, number)| {
+            let (x, y) = zone_origin(team, number);
+            let sub_zones: String = (0..4)
+                .map(|index| {
+                    let (sx, sy) = sub_zone_origin(team, number, index);
+                    format!(
+                        r##"<rect x="{sx}" y="{sy}" width="{SUB_ZONE}" height="{SUB_ZONE}" fill="none" stroke="#ffffff" stroke-opacity="0.4" stroke-dasharray="8 8"/><text x="{}" y="{}" font-size="24" fill="#ffffff" fill-opacity="0.6">{}</text>"##,
+                        sx + 8,
+                        sy + 28,
+                        ["A", "B", "C", "D"][index]
+                    )
+                })
+                .collect();
+
+            format!(
+                r##"{sub_zones}<rect x="{x}" y="{y}" width="{ZONE}" height="{ZONE}" fill="none" stroke="#ffffff" stroke-width="2"/><text x="{}" y="{}" font-size="64" text-anchor="middle" dominant-baseline="middle" fill="#ffffff" fill-opacity="0.5">{number}</text>"##,
+                x + ZONE / 2,
+                y + ZONE / 2
+            )
+        })
+        .collect();
+
+    format!(
+        r##"<rect x="0" y="0" width="{WIDTH}" height="{LENGTH}" fill="#e07b39" stroke="#ffffff" stroke-width="8"/>{zones}<line x1="{}" y1="{NET}" x2="{}" y2="{NET}" stroke="#111111" stroke-width="12"/>"##,
+        -MARGIN / 2,
+        WIDTH + MARGIN / 2
+    )
+}
+
+pub fn render_court() -> String {
+    svg(court_lines())
+}
+
+/// The side of the court the zone of an action refers to. Serves, attacks and
+/// freeballs are aimed at the opponent, receptions and passes stay on the
+/// team's own side and a block's zone is on the side of the blocked team.
+fn zone_side(team: Team, action_type: ActionType) -> Option<(Team, Zone)> {
+    match action_type {
+        ActionType::Serve(_, zone) | ActionType::Hit(_, zone) | ActionType::Freeball(zone) => {
+            zone.map(|zone| (team.get_opponent(), zone))
+        }
+        ActionType::Receive(_, zone) | ActionType::Pass(_, zone) => zone.map(|zone| (team, zone)),
+        ActionType::Block(blocked, zone) => zone.map(|zone| (blocked, zone)),
+        ActionType::Set => None,
+    }
+}
+
+/// The court with the path of the ball through every action of `rally` that
+/// has a zone, starting behind the server's end line.
+pub fn render_rally(rally: &Rally) -> String {
+    let start = rally.serve().map(|serve| match serve.team {
+        Team::Home => (WIDTH / 2, LENGTH + MARGIN / 2),
+        Team::Away => (WIDTH / 2, -MARGIN / 2),
+    });
+    let points: Vec<(i32, i32)> = start
+        .into_iter()
+        .chain(rally.actions.iter().filter_map(|action| {
+            zone_side(action.team, action.action_type)
+                .and_then(|(side, zone)| zone_center(side, zone))
+        }))
+        .collect();
+
+    let path = points
+        .iter()
+        .map(|(x, y)| format!("{x},{y}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let markers: String = points
+        .iter()
+        .enumerate()
+        .map(|(idx, (x, y))| {
+            format!(
+                r##"<circle cx="{x}" cy="{y}" r="22" fill="#1d4ed8"/><text x="{x}" y="{y}" font-size="24" text-anchor="middle" dominant-baseline="middle" fill="#ffffff">{idx}</text>"##
+            )
+        })
+        .collect();
+
+    svg(format!(
+        r##"{}<polyline points="{path}" fill="none" stroke="#1d4ed8" stroke-width="6"/>{markers}"##,
+        court_lines()
+    ))
+}
+
+/// Fill opacity of a cell relative to the busiest one.
+fn opacity(cell: &Percentage, max: u16) -> f32 {
+    match max {
+        0 => 0.0,
+        max => f32::from(cell.all) / f32::from(max) * 0.8,
+    }
+}
+
+/// The court with `heatmap` drawn on the side of `side`. Zones are shaded by
+/// how many actions went there and labelled with the count and win %.
+pub fn render_heatmap(side: Team, heatmap: &Heatmap) -> String {
+    let max_zone = heatmap.zones.iter().map(|cell| cell.all).max().unwrap_or(0);
+    let max_sub_zone = heatmap
+        .sub_zones
+        .iter()
+        .flatten()
+        .map(|cell| cell.all)
+        .max()
+        .unwrap_or(0);
+
+    let zones: String = (1..=9u8)
+        .map(|number| {
+            let cell = &heatmap.zones[usize::from(number - 1)];
+            let (x, y) = zone_origin(side, number);
+            let sub_zones: String = heatmap.sub_zones[usize::from(number - 1)]
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.all > 0)
+                .map(|(index, cell)| {
+                    let (sx, sy) = sub_zone_origin(side, number, index);
+                    format!(
+                        r##"<rect x="{sx}" y="{sy}" width="{SUB_ZONE}" height="{SUB_ZONE}" fill="#7f1d1d" fill-opacity="{:.2}"/>"##,
+                        opacity(cell, max_sub_zone)
+                    )
+                })
+                .collect();
+            let label = match cell.percentage {
+                Some(percentage) => format!("{} ({percentage:.0}%)", cell.all),
+                None => String::new(),
+            };
+
+            format!(
+                r##"<rect x="{x}" y="{y}" width="{ZONE}" height="{ZONE}" fill="#dc2626" fill-opacity="{:.2}"/>{sub_zones}<text x="{}" y="{}" font-size="36" text-anchor="middle" fill="#ffffff">{label}</text>"##,
+                opacity(cell, max_zone),
+                x + ZONE / 2,
+                y + ZONE - 24
+            )
+        })
+        .collect();
+
+    svg(format!("{}{zones}", court_lines()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::{parse, SubZone};
+    use crate::Config;
+
+    #[test]
+    fn zones_of_the_away_team_mirror_the_home_ones() {
+        let zones: [fn(Option<SubZone>) -> Zone; 9] = [
+            Zone::One,
+            Zone::Two,
+            Zone::Three,
+            Zone::Four,
+            Zone::Five,
+            Zone::Six,
+            Zone::Seven,
+            Zone::Eight,
+            Zone::Nine,
+        ];
+        let sub_zones = [
+            None,
+            Some(SubZone::A),
+            Some(SubZone::B),
+            Some(SubZone::C),
+            Some(SubZone::D),
+        ];
+
+        zones.iter().for_each(|zone| {
+            sub_zones.iter().for_each(|sub_zone| {
+                let (x, y) = zone_center(Team::Home, zone(*sub_zone)).unwrap();
+                let mirrored = zone_center(Team::Away, zone(*sub_zone)).unwrap();
+                assert_eq!(mirrored, (WIDTH - x, LENGTH - y));
+            })
+        });
+
+        // Home zone 4 is on the left of the front row, seen from behind the
+        // home end line
+        assert_eq!(zone_center(Team::Home, Zone::Four(None)), Some((150, 1050)));
+        assert_eq!(zone_center(Team::Home, Zone::Overpass), None);
+    }
+
+    #[test]
+    fn rallies_are_drawn_from_behind_the_server() {
+        let rally = parse(Config::default(), "@1S5 !2R2 !3E !4H5").unwrap();
+        let svg = render_rally(&rally);
+
+        // The serve start, the serve, the reception and the attack, the set
+        // has no zone
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.contains(r#"points="450,-50 150,1650 750,1050 750,150""#));
+    }
+}
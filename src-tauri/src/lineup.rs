@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+
+use crate::match_state::Player;
+use crate::parser::error::Reason;
+
+/// Back row positions 1, 5 and 6 as indices into `Lineup::positions`.
+const BACK_ROW: [usize; 3] = [0, 4, 5];
+/// Position 4, a libero rotating into it has to leave the court.
+const FRONT_LEFT: usize = 3;
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LiberoSwap {
+    pub libero: Player,
+    /// The player the libero is standing in for, they're off court meanwhile.
+    pub replaced: Player,
+}
+
+/// The players on court, `positions[0]` is position 1 (the server) and the
+/// rest follow the position numbers counter-clockwise.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Lineup {
+    pub positions: [Player; 6],
+    /// 1 for the starting lineup of the set, advanced on every rotation.
+    pub rotation: u8,
+    pub libero: Option<LiberoSwap>,
+}
+
+impl Lineup {
+    pub fn new(positions: [Player; 6]) -> Result<Self, Reason> {
+        let has_duplicates = positions
+            .iter()
+            .enumerate()
+            .any(|(idx, player)| positions[idx + 1..].contains(player));
+
+        match has_duplicates {
+            true => Err(Reason::duplicate_lineup_player()),
+            false => Ok(Self {
+                positions,
+                rotation: 1,
+                libero: None,
+            }),
+        }
+    }
+
+    pub fn server(self) -> Player {
+        self.positions[0]
+    }
+
+    pub fn is_on_court(self, player: Player) -> bool {
+        self.positions.contains(&player)
+    }
+
+    /// Rotates clockwise, the player in position 2 becomes the server. A libero
+    /// rotating to the front row is automatically replaced again.
+    pub fn rotate(self) -> Self {
+        let mut positions = self.positions;
+        positions.rotate_left(1);
+
+        let libero = match self.libero {
+            Some(swap) if positions[FRONT_LEFT] == swap.libero => {
+                positions[FRONT_LEFT] = swap.replaced;
+                None
+            }
+            libero => libero,
+        };
+
+        Self {
+            positions,
+            rotation: self.rotation % 6 + 1,
+            libero,
+        }
+    }
+
+    /// The libero takes the place of a back row `player`, or leaves the court
+    /// again if `player` is the one it replaced. Libero replacements aren't
+    /// substitutions.
+    pub fn swap_libero(
+        self,
+        libero: Player,
+        player: Player,
+        libero_can_serve: bool,
+    ) -> Result<Self, Reason> {
+        let mut positions = self.positions;
+
+        match self.libero {
+            Some(swap) if swap.libero == libero && swap.replaced == player => {
+                let position = self
+                    .positions
+                    .iter()
+                    .position(|p| *p == libero)
+                    .ok_or(Reason::substituted_player_not_on_court())?;
+                positions[position] = player;
+
+                Ok(Self {
+                    positions,
+                    libero: None,
+                    ..self
+                })
+            }
+            Some(_) => Err(Reason::libero_already_on_court()),
+            None => {
+                let position = self
+                    .positions
+                    .iter()
+                    .position(|p| *p == player)
+                    .ok_or(Reason::substituted_player_not_on_court())?;
+                let allowed = BACK_ROW
+                    .into_iter()
+                    .filter(|p| libero_can_serve || *p != 0)
+                    .any(|p| p == position);
+
+                if !allowed {
+                    return Err(Reason::libero_not_back_row());
+                }
+
+                positions[position] = libero;
+
+                Ok(Self {
+                    positions,
+                    libero: Some(LiberoSwap {
+                        libero,
+                        replaced: player,
+                    }),
+                    ..self
+                })
+            }
+        }
+    }
+
+    pub fn substitute(self, player_out: Player, player_in: Player) -> Result<Self, Reason> {
+        if self.is_on_court(player_in) {
+            return Err(Reason::substitute_already_on_court());
+        }
+
+        let position = self
+            .positions
+            .iter()
+            .position(|player| *player == player_out)
+            .ok_or(Reason::substituted_player_not_on_court())?;
+
+        let mut positions = self.positions;
+        positions[position] = player_in;
+
+        Ok(Self { positions, ..self })
+    }
+}
+
+/// A player replaced by a substitute during a set, the starter may only come
+/// back in for that same substitute and the substitute can't re-enter after.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SubstitutionPair {
+    pub starter: Player,
+    pub substitute: Player,
+    pub returned: bool,
+}
+
+/// Regular substitutions of a team in the current set.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Substitutions {
+    pub pairs: Vec<SubstitutionPair>,
+    pub count: u8,
+}
+
+impl Substitutions {
+    pub fn new() -> Self {
+        Self {
+            pairs: Vec::new(),
+            count: 0,
+        }
+    }
+
+    pub fn record(
+        self,
+        player_out: Player,
+        player_in: Player,
+        max_substitutions: u8,
+    ) -> Result<Self, Reason> {
+        if self.count >= max_substitutions {
+            return Err(Reason::too_many_substitutions());
+        }
+
+        let mut pairs = self.pairs;
+        let existing = pairs.iter().position(|pair| {
+            [pair.starter, pair.substitute].contains(&player_out)
+                || [pair.starter, pair.substitute].contains(&player_in)
+        });
+
+        match existing {
+            None => pairs.push(SubstitutionPair {
+                starter: player_out,
+                substitute: player_in,
+                returned: false,
+            }),
+            Some(idx) => {
+                let pair = pairs[idx];
+
+                if pair.returned || pair.substitute != player_out || pair.starter != player_in {
+                    return Err(Reason::illegal_substitution());
+                }
+
+                pairs[idx] = SubstitutionPair {
+                    returned: true,
+                    ..pair
+                };
+            }
+        }
+
+        Ok(Self {
+            pairs,
+            count: self.count + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lineup() -> Lineup {
+        Lineup::new([1, 2, 3, 4, 5, 6].map(Player)).unwrap()
+    }
+
+    fn numbers(lineup: Lineup) -> [u8; 6] {
+        lineup.positions.map(|player| player.0)
+    }
+
+    #[test]
+    fn rotation_moves_position_two_to_serve() {
+        let rotated = (0..6).fold(lineup(), |lineup, _| lineup.rotate());
+
+        assert_eq!(numbers(lineup().rotate()), [2, 3, 4, 5, 6, 1]);
+        assert_eq!(lineup().rotate().rotation, 2);
+        assert_eq!(numbers(rotated), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(rotated.rotation, 1);
+    }
+
+    #[test]
+    fn libero_leaves_the_court_rotating_into_position_four() {
+        let swapped = lineup().swap_libero(Player(10), Player(5), false).unwrap();
+        let rotated = swapped.rotate();
+
+        assert_eq!(numbers(swapped), [1, 2, 3, 4, 10, 6]);
+        assert_eq!(numbers(rotated), [2, 3, 4, 5, 6, 1]);
+        assert!(rotated.libero.is_none());
+    }
+
+    #[test]
+    fn libero_stays_in_the_back_row() {
+        let rotated = lineup()
+            .swap_libero(Player(10), Player(6), false)
+            .unwrap()
+            .rotate();
+
+        assert_eq!(numbers(rotated), [2, 3, 4, 5, 10, 1]);
+        assert!(rotated
+            .libero
+            .is_some_and(|swap| swap.replaced == Player(6)));
+    }
+
+    #[test]
+    fn libero_only_serves_if_allowed() {
+        assert!(lineup().swap_libero(Player(10), Player(1), false).is_err());
+        assert!(lineup().swap_libero(Player(10), Player(1), true).is_ok());
+        assert!(lineup().swap_libero(Player(10), Player(4), true).is_err());
+    }
+
+    #[test]
+    fn starter_returns_once_for_their_substitute() {
+        let subs = Substitutions::new()
+            .record(Player(1), Player(7), 6)
+            .unwrap();
+
+        assert!(subs.clone().record(Player(7), Player(8), 6).is_err());
+        assert!(subs.clone().record(Player(2), Player(7), 6).is_err());
+
+        let returned = subs.record(Player(7), Player(1), 6).unwrap();
+
+        assert_eq!(returned.count, 2);
+        assert!(returned.pairs[0].returned);
+        assert!(returned.clone().record(Player(1), Player(7), 6).is_err());
+        assert!(returned.record(Player(1), Player(8), 6).is_err());
+    }
+
+    #[test]
+    fn substitutions_are_limited_per_set() {
+        let subs = (1..=6).try_fold(Substitutions::new(), |subs, player| {
+            subs.record(Player(player), Player(player + 10), 6)
+        });
+
+        assert!(subs.unwrap().record(Player(20), Player(21), 6).is_err());
+    }
+}
@@ -1,44 +1,485 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use match_state::UpdateMatchState;
+use std::sync::Mutex;
+
+use lineup::Lineup;
+use match_history::{InvalidEvent, MatchEvent, MatchHistory, PlayedRally};
+use match_rules::{Discipline, MatchRules};
+use match_state::{Player, Team};
+use parser::error::Reason;
 use serde::Serialize;
+use stats::attack::AttackReport;
+use stats::beach::BeachStats;
+use stats::clutch::ClutchReport;
+use stats::heatmap::HeatmapReport;
+use stats::libero::LiberoReport;
+use stats::momentum::MomentumReport;
+use stats::phase::PhaseReport;
+use stats::points::PointsReport;
+use stats::rally_length::RallyLengthReport;
+use stats::reception::ReceptionReport;
+use stats::rotation::RotationReport;
+use stats::serving::ServingStats;
+use stats::setting::SettingReport;
+use stats::timeouts::TimeoutReport;
+use stats::Skill;
+use tauri::Manager;
 
+mod court;
+mod lineup;
+mod match_history;
+mod match_rules;
 mod match_state;
 mod parser;
+mod stats;
 mod utils;
 
+const MATCH_UPDATED_EVENT: &str = "match-updated";
+
 #[derive(Clone, Copy)]
 struct Config {
     away_prefix: char,
     home_prefix: char,
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            away_prefix: '@',
+            home_prefix: '!',
+        }
+    }
+}
+
+/// The match being scouted, owned by the backend so every window observes the
+/// same history and the webview never gets to hand us a state of its own.
+struct CurrentMatch(Mutex<MatchHistory>);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MatchSnapshot {
+    history: MatchHistory,
+    state: match_state::MatchState,
+    invalid: Vec<InvalidEvent>,
+}
+
+impl MatchSnapshot {
+    fn new(config: Config, history: MatchHistory) -> Self {
+        let (state, invalid) = history.state_and_invalid_events(config);
+
+        Self {
+            history,
+            state,
+            invalid,
+        }
+    }
+}
+
 #[derive(Serialize)]
-enum ParseRallyResult {
-    Ok(match_state::MatchState),
-    Fail(parser::error::Reason),
+enum CommandResult<T> {
+    Ok(T),
+    Fail(Reason),
+}
+
+impl<T> From<Result<T, Reason>> for CommandResult<T> {
+    fn from(result: Result<T, Reason>) -> Self {
+        match result {
+            Ok(val) => CommandResult::Ok(val),
+            Err(reason) => CommandResult::Fail(reason),
+        }
+    }
+}
+
+/// Applies `update` to the current match and notifies every window about the
+/// new snapshot, the current match is left untouched if the update fails.
+fn update_match(
+    app: &tauri::AppHandle,
+    current_match: &CurrentMatch,
+    update: impl FnOnce(MatchHistory) -> Result<MatchHistory, Reason>,
+) -> Result<MatchSnapshot, Reason> {
+    let mut history = current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())?;
+
+    let new_history = update(history.clone())?;
+    let snapshot = MatchSnapshot::new(Config::default(), new_history.clone());
+    *history = new_history;
+
+    // Failing to notify other windows shouldn't fail the command that caused it
+    let _ = app.emit_all(MATCH_UPDATED_EVENT, snapshot.clone());
+
+    Ok(snapshot)
+}
+
+/// Computes a report over the valid rallies of the current match.
+fn with_rallies<T>(
+    current_match: &CurrentMatch,
+    report: impl FnOnce(&[PlayedRally]) -> T,
+) -> Result<T, Reason> {
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .map(|history| report(&history.rallies(Config::default())))
+}
+
+#[tauri::command]
+fn get_rules_presets() -> Vec<match_rules::RulesPreset> {
+    MatchRules::presets()
+}
+
+#[tauri::command]
+fn get_match(current_match: tauri::State<CurrentMatch>) -> CommandResult<MatchSnapshot> {
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .map(|history| MatchSnapshot::new(Config::default(), history.clone()))
+        .into()
+}
+
+#[tauri::command]
+fn new_match(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    rules: MatchRules,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |_| {
+        rules.validate().map(MatchHistory::new)
+    })
+    .into()
+}
+
+#[tauri::command]
+fn parse_rally(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    rally: &str,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.push(Config::default(), MatchEvent::Rally(rally.to_string()))
+    })
+    .into()
+}
+
+#[tauri::command]
+fn set_lineup(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+    positions: [Player; 6],
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        Lineup::new(positions)
+            .and_then(|lineup| history.push(Config::default(), MatchEvent::Lineup { team, lineup }))
+    })
+    .into()
+}
+
+#[tauri::command]
+fn substitute(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+    player_out: Player,
+    player_in: Player,
+    exceptional: bool,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.push(
+            Config::default(),
+            MatchEvent::Substitution {
+                team,
+                player_out,
+                player_in,
+                exceptional,
+            },
+        )
+    })
+    .into()
+}
+
+#[tauri::command]
+fn designate_libero(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+    libero: Player,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.push(Config::default(), MatchEvent::Libero { team, libero })
+    })
+    .into()
+}
+
+#[tauri::command]
+fn swap_libero(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+    libero: Player,
+    player: Player,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.push(
+            Config::default(),
+            MatchEvent::LiberoReplacement {
+                team,
+                libero,
+                player,
+            },
+        )
+    })
+    .into()
+}
+
+#[tauri::command]
+fn call_timeout(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.push(Config::default(), MatchEvent::Timeout { team })
+    })
+    .into()
+}
+
+#[tauri::command]
+fn undo(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| history.undo()).into()
+}
+
+#[tauri::command]
+fn redo(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| history.redo()).into()
+}
+
+#[tauri::command]
+fn edit_rally(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    n: usize,
+    rally: &str,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| {
+        history.replace(Config::default(), n, MatchEvent::Rally(rally.to_string()))
+    })
+    .into()
 }
 
 #[tauri::command]
-fn parse_rally(rally: &str, current_stats: match_state::MatchState) -> ParseRallyResult {
-    let config = Config {
-        away_prefix: '@',
-        home_prefix: '!',
+fn delete_event(
+    app: tauri::AppHandle,
+    current_match: tauri::State<CurrentMatch>,
+    n: usize,
+) -> CommandResult<MatchSnapshot> {
+    update_match(&app, &current_match, |history| history.delete(n)).into()
+}
+
+#[tauri::command]
+fn get_beach_stats(current_match: tauri::State<CurrentMatch>) -> CommandResult<BeachStats> {
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .and_then(|history| match history.rules().discipline {
+            Discipline::Beach(_) => Ok(BeachStats::new(&history.rallies(Config::default()))),
+            Discipline::Indoor => Err(Reason::not_a_beach_match()),
+        })
+        .into()
+}
+
+#[tauri::command]
+fn get_serving_stats(current_match: tauri::State<CurrentMatch>) -> CommandResult<ServingStats> {
+    with_rallies(&current_match, ServingStats::new).into()
+}
+
+#[tauri::command]
+fn get_attack_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<AttackReport> {
+    with_rallies(&current_match, AttackReport::new).into()
+}
+
+#[tauri::command]
+fn get_setting_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<SettingReport> {
+    with_rallies(&current_match, SettingReport::new).into()
+}
+
+#[tauri::command]
+fn get_phase_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<PhaseReport> {
+    with_rallies(&current_match, PhaseReport::new).into()
+}
+
+#[tauri::command]
+fn get_points_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<PointsReport> {
+    with_rallies(&current_match, PointsReport::new).into()
+}
+
+#[tauri::command]
+fn get_momentum_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<MomentumReport> {
+    with_rallies(&current_match, MomentumReport::new).into()
+}
+
+#[tauri::command]
+fn get_rally_length_report(
+    current_match: tauri::State<CurrentMatch>,
+) -> CommandResult<RallyLengthReport> {
+    with_rallies(&current_match, RallyLengthReport::new).into()
+}
+
+#[tauri::command]
+fn get_clutch_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<ClutchReport> {
+    with_rallies(&current_match, ClutchReport::new).into()
+}
+
+#[tauri::command]
+fn get_heatmaps(current_match: tauri::State<CurrentMatch>) -> CommandResult<HeatmapReport> {
+    with_rallies(&current_match, HeatmapReport::new).into()
+}
+
+/// The heatmaps as CSV for spreadsheets and other tools.
+#[tauri::command]
+fn export_heatmaps(current_match: tauri::State<CurrentMatch>) -> CommandResult<String> {
+    with_rallies(&current_match, |rallies| {
+        HeatmapReport::new(rallies).to_csv()
+    })
+    .into()
+}
+
+#[tauri::command]
+fn render_court_svg() -> String {
+    court::render_court()
+}
+
+#[tauri::command]
+fn render_rally_svg(current_match: tauri::State<CurrentMatch>, n: usize) -> CommandResult<String> {
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .and_then(|history| history.rally_at(Config::default(), n))
+        .map(|rally| court::render_rally(&rally))
+        .into()
+}
+
+/// Serves and attacks of `team` are drawn on the opponent's side of the court,
+/// its receptions on its own side.
+#[tauri::command]
+fn render_heatmap_svg(
+    current_match: tauri::State<CurrentMatch>,
+    team: Team,
+    skill: Skill,
+) -> CommandResult<String> {
+    let side = match skill {
+        Skill::Receive => team,
+        _ => team.get_opponent(),
     };
 
-    let update_state =
-        parser::parser::parse(config, rally).map(|actions| UpdateMatchState::new(actions));
+    with_rallies(&current_match, HeatmapReport::new)
+        .and_then(|report| {
+            report
+                .team(team)
+                .total
+                .skill(skill)
+                .map(|heatmap| court::render_heatmap(side, heatmap))
+                .ok_or(Reason::no_heatmap_for_skill())
+        })
+        .into()
+}
 
-    match update_state {
-        Ok(update) => ParseRallyResult::Ok(current_stats.update(update)),
-        Err(reasons) => ParseRallyResult::Fail(reasons),
-    }
+#[tauri::command]
+fn get_rotation_stats(current_match: tauri::State<CurrentMatch>) -> CommandResult<RotationReport> {
+    with_rallies(&current_match, RotationReport::new).into()
+}
+
+#[tauri::command]
+fn get_libero_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<LiberoReport> {
+    with_rallies(&current_match, LiberoReport::new).into()
+}
+
+#[tauri::command]
+fn get_reception_report(
+    current_match: tauri::State<CurrentMatch>,
+) -> CommandResult<ReceptionReport> {
+    with_rallies(&current_match, ReceptionReport::new).into()
+}
+
+#[tauri::command]
+fn get_timeout_report(current_match: tauri::State<CurrentMatch>) -> CommandResult<TimeoutReport> {
+    let config = Config::default();
+
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .and_then(|history| {
+            history
+                .state(config)
+                .map(|state| TimeoutReport::new(&state, &history.rallies(config)))
+        })
+        .into()
+}
+
+#[tauri::command]
+fn state_at(
+    current_match: tauri::State<CurrentMatch>,
+    n: usize,
+) -> CommandResult<match_state::MatchState> {
+    current_match
+        .0
+        .lock()
+        .map_err(|_| Reason::match_unavailable())
+        .and_then(|history| history.state_at(Config::default(), n))
+        .into()
 }
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![parse_rally])
+        .manage(CurrentMatch(Mutex::new(MatchHistory::new(
+            MatchRules::default(),
+        ))))
+        .invoke_handler(tauri::generate_handler![
+            get_rules_presets,
+            get_match,
+            new_match,
+            parse_rally,
+            set_lineup,
+            substitute,
+            designate_libero,
+            swap_libero,
+            call_timeout,
+            undo,
+            redo,
+            edit_rally,
+            delete_event,
+            get_beach_stats,
+            get_serving_stats,
+            get_attack_report,
+            get_setting_report,
+            get_phase_report,
+            get_points_report,
+            get_momentum_report,
+            get_rally_length_report,
+            get_clutch_report,
+            get_heatmaps,
+            export_heatmaps,
+            render_court_svg,
+            render_rally_svg,
+            render_heatmap_svg,
+            get_rotation_stats,
+            get_libero_report,
+            get_reception_report,
+            get_timeout_report,
+            state_at
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
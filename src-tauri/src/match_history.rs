@@ -0,0 +1,398 @@
+use std::mem::discriminant;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::lineup::Lineup;
+use crate::match_rules::MatchRules;
+use crate::match_state::{MatchState, Player, Team, UpdateMatchState};
+use crate::parser::error::Reason;
+use crate::parser::parser::{parse, Rally};
+use crate::Config;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all_fields = "camelCase")]
+pub enum MatchEvent {
+    Rally(String),
+    Lineup {
+        team: Team,
+        lineup: Lineup,
+    },
+    Substitution {
+        team: Team,
+        player_out: Player,
+        player_in: Player,
+        exceptional: bool,
+    },
+    Libero {
+        team: Team,
+        libero: Player,
+    },
+    LiberoReplacement {
+        team: Team,
+        libero: Player,
+        player: Player,
+    },
+    Timeout {
+        team: Team,
+    },
+}
+
+impl MatchEvent {
+    /// Applies the event to `state`, also returning the parsed rally for rally events.
+    fn apply(
+        &self,
+        config: Config,
+        state: MatchState,
+        at: Option<u64>,
+    ) -> Result<(MatchState, Option<Rally>), Reason> {
+        match self {
+            MatchEvent::Rally(rally) => parse(config, rally).and_then(|rally| {
+                state
+                    .update(UpdateMatchState {
+                        at,
+                        ..UpdateMatchState::new(rally.clone())
+                    })
+                    .map(|state| (state, Some(rally)))
+            }),
+            MatchEvent::Lineup { team, lineup } => {
+                state.set_lineup(*team, *lineup).map(|state| (state, None))
+            }
+            MatchEvent::Substitution {
+                team,
+                player_out,
+                player_in,
+                exceptional,
+            } => state
+                .substitute(*team, *player_out, *player_in, *exceptional)
+                .map(|state| (state, None)),
+            MatchEvent::Libero { team, libero } => state
+                .designate_libero(*team, *libero)
+                .map(|state| (state, None)),
+            MatchEvent::LiberoReplacement {
+                team,
+                libero,
+                player,
+            } => state
+                .swap_libero(*team, *libero, *player)
+                .map(|state| (state, None)),
+            MatchEvent::Timeout { team } => state.call_timeout(*team).map(|state| (state, None)),
+        }
+    }
+}
+
+/// An event as stored in the history, along with the Unix time in seconds it
+/// was first recorded at. Edited events keep their original time.
+#[derive(Serialize, Clone)]
+pub struct LoggedEvent {
+    #[serde(flatten)]
+    pub event: MatchEvent,
+    pub at: Option<u64>,
+}
+
+impl LoggedEvent {
+    fn now(event: MatchEvent) -> Self {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|time| time.as_secs());
+
+        Self { event, at }
+    }
+
+    fn apply(
+        &self,
+        config: Config,
+        state: MatchState,
+    ) -> Result<(MatchState, Option<Rally>), Reason> {
+        self.event.apply(config, state, self.at)
+    }
+}
+
+/// A rally together with the state of the match right before it was played.
+#[derive(Clone)]
+pub struct PlayedRally {
+    pub before: MatchState,
+    pub rally: Rally,
+}
+
+struct Replay {
+    state: MatchState,
+    invalid: Vec<InvalidEvent>,
+    rallies: Vec<PlayedRally>,
+}
+
+/// A logged event that can no longer be applied, usually because an earlier
+/// rally was edited or deleted. It stays in the log so it can be fixed.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidEvent {
+    pub index: usize,
+    pub reason: Reason,
+}
+
+/// Append-only log of everything that happened in a match, the `MatchState`
+/// is never stored and always derived by replaying the events.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchHistory {
+    rules: MatchRules,
+    events: Vec<LoggedEvent>,
+    undone: Vec<LoggedEvent>,
+}
+
+impl MatchHistory {
+    pub fn new(rules: MatchRules) -> Self {
+        Self {
+            rules,
+            events: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn push(self, config: Config, event: MatchEvent) -> Result<Self, Reason> {
+        let event = LoggedEvent::now(event);
+        let state = self.state(config)?;
+        event.apply(config, state)?;
+
+        let mut events = self.events;
+        events.push(event);
+
+        Ok(Self {
+            events,
+            undone: Vec::new(),
+            ..self
+        })
+    }
+
+    pub fn undo(self) -> Result<Self, Reason> {
+        let mut new_history = self.clone();
+        let event = new_history.events.pop().ok_or(Reason::nothing_to_undo())?;
+        new_history.undone.push(event);
+
+        Ok(new_history)
+    }
+
+    pub fn redo(self) -> Result<Self, Reason> {
+        let mut new_history = self.clone();
+        let event = new_history.undone.pop().ok_or(Reason::nothing_to_redo())?;
+        new_history.events.push(event);
+
+        Ok(new_history)
+    }
+
+    /// Replaces event `n`, the replacement has to be valid at that point in the
+    /// match but later events are only re-validated on replay.
+    pub fn replace(self, config: Config, n: usize, event: MatchEvent) -> Result<Self, Reason> {
+        if n >= self.events.len() {
+            return Err(Reason::no_such_event());
+        }
+
+        if discriminant(&self.events[n].event) != discriminant(&event) {
+            return Err(Reason::event_kind_mismatch());
+        }
+
+        let event = LoggedEvent {
+            event,
+            at: self.events[n].at,
+        };
+        let state = self.state_at(config, n)?;
+        event.apply(config, state)?;
+
+        let mut events = self.events;
+        events[n] = event;
+
+        Ok(Self {
+            events,
+            undone: Vec::new(),
+            ..self
+        })
+    }
+
+    /// Removes event `n` whatever its kind, e.g. a lineup entered by mistake.
+    /// Later events are only re-validated on replay.
+    pub fn delete(self, n: usize) -> Result<Self, Reason> {
+        if n >= self.events.len() {
+            return Err(Reason::no_such_event());
+        }
+
+        let mut events = self.events;
+        events.remove(n);
+
+        Ok(Self {
+            events,
+            undone: Vec::new(),
+            ..self
+        })
+    }
+
+    /// Replays the first `n` events, events that fail to apply are skipped and
+    /// reported instead of aborting the replay.
+    fn replay(&self, config: Config, n: usize) -> Replay {
+        let initial = Replay {
+            state: MatchState::new(self.rules.clone()),
+            invalid: Vec::new(),
+            rallies: Vec::new(),
+        };
+
+        self.events
+            .iter()
+            .take(n)
+            .enumerate()
+            .fold(initial, |mut replay, (index, event)| {
+                match event.apply(config, replay.state.clone()) {
+                    Ok((state, rally)) => {
+                        if let Some(rally) = rally {
+                            replay.rallies.push(PlayedRally {
+                                before: replay.state,
+                                rally,
+                            });
+                        }
+                        Replay { state, ..replay }
+                    }
+                    Err(reason) => {
+                        replay.invalid.push(InvalidEvent { index, reason });
+                        replay
+                    }
+                }
+            })
+    }
+
+    /// State of the match after the first `n` events.
+    pub fn state_at(&self, config: Config, n: usize) -> Result<MatchState, Reason> {
+        if n > self.events.len() {
+            return Err(Reason::no_such_event());
+        }
+
+        Ok(self.replay(config, n).state)
+    }
+
+    pub fn state(&self, config: Config) -> Result<MatchState, Reason> {
+        self.state_at(config, self.events.len())
+    }
+
+    /// State of the match after every event along with the events that failed
+    /// to apply, from a single replay.
+    pub fn state_and_invalid_events(&self, config: Config) -> (MatchState, Vec<InvalidEvent>) {
+        let Replay { state, invalid, .. } = self.replay(config, self.events.len());
+
+        (state, invalid)
+    }
+
+    /// The rally recorded as event `n`, which has to be a valid rally.
+    pub fn rally_at(&self, config: Config, n: usize) -> Result<Rally, Reason> {
+        let event = self.events.get(n).ok_or(Reason::no_such_event())?;
+
+        match event.apply(config, self.replay(config, n).state) {
+            Ok((_, Some(rally))) => Ok(rally),
+            _ => Err(Reason::no_such_event()),
+        }
+    }
+
+    /// Every rally that's part of the match, skipping invalid ones.
+    pub fn rallies(&self, config: Config) -> Vec<PlayedRally> {
+        self.replay(config, self.events.len()).rallies
+    }
+
+    pub fn rules(&self) -> &MatchRules {
+        &self.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_rallies(history: MatchHistory, rallies: &[&str]) -> Result<MatchHistory, Reason> {
+        rallies.iter().try_fold(history, |history, rally| {
+            history.push(Config::default(), MatchEvent::Rally(rally.to_string()))
+        })
+    }
+
+    fn score(history: &MatchHistory) -> (u8, u8, u8) {
+        let state = history.state(Config::default()).unwrap();
+
+        (
+            state.current_set(),
+            state.points(Team::Away),
+            state.points(Team::Home),
+        )
+    }
+
+    #[test]
+    fn redo_restores_what_undo_took_back() {
+        let history = push_rallies(MatchHistory::new(MatchRules::default()), &["@1S5", "@1S5"]);
+        let undone = history.unwrap().undo().unwrap();
+
+        assert_eq!(score(&undone), (1, 1, 0));
+        assert_eq!(score(&undone.clone().redo().unwrap()), (1, 2, 0));
+        assert!(undone.undo().unwrap().undo().is_err());
+    }
+
+    #[test]
+    fn pushing_after_undo_drops_the_undone_events() {
+        let history = push_rallies(MatchHistory::new(MatchRules::default()), &["@1S5", "@1S5"]);
+        let history = push_rallies(history.unwrap().undo().unwrap(), &["@2S5"]).unwrap();
+
+        assert_eq!(score(&history), (1, 2, 0));
+        assert!(history.redo().is_err());
+    }
+
+    #[test]
+    fn deleting_a_rally_replays_the_rest_of_the_match() {
+        let rules = MatchRules {
+            points_per_set: 3,
+            min_margin: 1,
+            ..MatchRules::default()
+        };
+        let history = push_rallies(MatchHistory::new(rules), &["@1S5", "@1S5", "@1S5", "!1S5"]);
+        let history = history.unwrap();
+
+        assert_eq!(score(&history), (2, 0, 1));
+
+        // The first set is no longer over, so Away still serves and Home's
+        // serve that opened the second set can't be applied anymore
+        let deleted = history.delete(1).unwrap();
+        let (_, invalid_events) = deleted.state_and_invalid_events(Config::default());
+
+        assert_eq!(score(&deleted), (1, 2, 0));
+        assert_eq!(invalid_events.len(), 1);
+        assert_eq!(invalid_events[0].index, 2);
+        assert!(push_rallies(deleted.clone(), &["!1S5"]).is_err());
+        assert_eq!(score(&push_rallies(deleted, &["@1S5"]).unwrap()), (2, 0, 0));
+    }
+
+    #[test]
+    fn editing_or_deleting_clears_the_undone_events() {
+        let history = push_rallies(MatchHistory::new(MatchRules::default()), &["@1S5", "@1S5"]);
+        let undone = history.unwrap().undo().unwrap();
+        let config = Config::default();
+
+        assert!(undone.clone().delete(0).unwrap().redo().is_err());
+        assert!(undone
+            .replace(config, 0, MatchEvent::Rally("@2S5".to_string()))
+            .unwrap()
+            .redo()
+            .is_err());
+    }
+
+    #[test]
+    fn rally_at_only_finds_valid_rallies() {
+        let config = Config::default();
+        let history = MatchHistory::new(MatchRules::default())
+            .push(config, MatchEvent::Rally("@1S5".to_string()))
+            .and_then(|history| history.push(config, MatchEvent::Timeout { team: Team::Home }))
+            .and_then(|history| history.push(config, MatchEvent::Rally("@1S5".to_string())))
+            .and_then(|history| history.replace(config, 0, MatchEvent::Rally("!1S5".to_string())))
+            .unwrap();
+        let (_, invalid_events) = history.state_and_invalid_events(config);
+        let invalid: Vec<usize> = invalid_events.iter().map(|invalid| invalid.index).collect();
+
+        assert_eq!(invalid, vec![2]);
+        assert!(history.rally_at(config, 0).is_ok());
+        // A timeout, a rally by the team that no longer serves and no event at all
+        assert!(history.rally_at(config, 1).is_err());
+        assert!(history.rally_at(config, 2).is_err());
+        assert!(history.rally_at(config, 3).is_err());
+    }
+}
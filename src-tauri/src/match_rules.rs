@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+use crate::parser::error::Reason;
+
+/// Keeps the number of sets in a match, and so the set numbers, within a `u8`.
+const MAX_SETS_TO_WIN: u8 = u8::MAX / 2;
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct BeachRules {
+    pub side_switch_every: u8,
+    pub deciding_side_switch_every: u8,
+    /// Total points in the set at which the technical timeout is taken, there's
+    /// none in the deciding set.
+    pub technical_timeout_at: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum Discipline {
+    Indoor,
+    Beach(BeachRules),
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRules {
+    pub discipline: Discipline,
+    pub sets_to_win: u8,
+    pub points_per_set: u8,
+    pub deciding_set_points: u8,
+    pub min_margin: u8,
+    /// A team reaching the cap wins the set regardless of the margin.
+    pub point_cap: Option<u8>,
+    /// Some leagues let the libero serve in one rotation.
+    pub libero_can_serve: bool,
+    /// Regular substitutions per team per set, exceptional ones don't count.
+    pub max_substitutions: u8,
+    pub timeouts_per_set: u8,
+    /// Scores of the leading team at which an indoor technical timeout is
+    /// taken, there are none in the deciding set.
+    pub technical_timeouts: Vec<u8>,
+}
+
+impl Default for MatchRules {
+    /// Indoor best-of-5 to 25, the deciding set to 15.
+    fn default() -> Self {
+        Self {
+            discipline: Discipline::Indoor,
+            sets_to_win: 3,
+            points_per_set: 25,
+            deciding_set_points: 15,
+            min_margin: 2,
+            point_cap: None,
+            libero_can_serve: false,
+            max_substitutions: 6,
+            timeouts_per_set: 2,
+            technical_timeouts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct RulesPreset {
+    pub name: &'static str,
+    pub rules: MatchRules,
+}
+
+impl MatchRules {
+    pub fn presets() -> Vec<RulesPreset> {
+        vec![
+            RulesPreset {
+                name: "Best of 5",
+                rules: Self::default(),
+            },
+            RulesPreset {
+                name: "Best of 5 with technical timeouts",
+                rules: Self {
+                    technical_timeouts: vec![8, 16],
+                    ..Self::default()
+                },
+            },
+            RulesPreset {
+                name: "Best of 3",
+                rules: Self {
+                    sets_to_win: 2,
+                    ..Self::default()
+                },
+            },
+            RulesPreset {
+                name: "Best of 3 to 21",
+                rules: Self {
+                    sets_to_win: 2,
+                    points_per_set: 21,
+                    ..Self::default()
+                },
+            },
+            RulesPreset {
+                name: "Best of 3 to 21, capped at 23",
+                rules: Self {
+                    sets_to_win: 2,
+                    points_per_set: 21,
+                    point_cap: Some(23),
+                    ..Self::default()
+                },
+            },
+            RulesPreset {
+                name: "Beach",
+                rules: Self::beach(),
+            },
+        ]
+    }
+
+    /// Beach best-of-3 to 21, the deciding set to 15.
+    pub fn beach() -> Self {
+        Self {
+            discipline: Discipline::Beach(BeachRules {
+                side_switch_every: 7,
+                deciding_side_switch_every: 5,
+                technical_timeout_at: Some(21),
+            }),
+            sets_to_win: 2,
+            points_per_set: 21,
+            deciding_set_points: 15,
+            min_margin: 2,
+            point_cap: None,
+            libero_can_serve: false,
+            max_substitutions: 6,
+            timeouts_per_set: 1,
+            technical_timeouts: Vec::new(),
+        }
+    }
+
+    pub fn validate(self) -> Result<Self, Reason> {
+        let cap_too_low = self
+            .point_cap
+            .is_some_and(|cap| cap < self.points_per_set || cap < self.deciding_set_points);
+        let side_switches = match self.discipline {
+            Discipline::Indoor => vec![],
+            Discipline::Beach(beach) => {
+                vec![beach.side_switch_every, beach.deciding_side_switch_every]
+            }
+        };
+        let has_zero = [
+            self.sets_to_win,
+            self.points_per_set,
+            self.deciding_set_points,
+            self.min_margin,
+        ]
+        .into_iter()
+        .chain(side_switches)
+        .any(|n| n == 0);
+
+        let too_many_sets = self.sets_to_win > MAX_SETS_TO_WIN;
+
+        match has_zero || cap_too_low || too_many_sets {
+            true => Err(Reason::invalid_rules()),
+            false => Ok(self),
+        }
+    }
+
+    /// Whether the set after `sets_played` completed sets is the deciding one.
+    pub fn is_deciding_set(&self, sets_played: u8) -> bool {
+        sets_played == (self.sets_to_win - 1) * 2
+    }
+
+    pub fn set_points(&self, sets_played: u8) -> u8 {
+        match self.is_deciding_set(sets_played) {
+            true => self.deciding_set_points,
+            false => self.points_per_set,
+        }
+    }
+
+    pub fn is_set_won(&self, sets_played: u8, points: u8, opponent_points: u8) -> bool {
+        let reached_cap = self.point_cap.is_some_and(|cap| points >= cap);
+        let reached_target = points >= self.set_points(sets_played)
+            && points.saturating_sub(opponent_points) >= self.min_margin;
+
+        reached_cap || reached_target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_needs_target_and_margin() {
+        let rules = MatchRules::default();
+
+        assert!(rules.is_set_won(0, 25, 23));
+        assert!(!rules.is_set_won(0, 25, 24));
+        assert!(!rules.is_set_won(0, 24, 22));
+        assert!(rules.is_set_won(0, 31, 29));
+    }
+
+    #[test]
+    fn deciding_set_is_shorter() {
+        let rules = MatchRules::default();
+
+        assert!(!rules.is_deciding_set(3));
+        assert!(rules.is_deciding_set(4));
+        assert!(rules.is_set_won(4, 15, 13));
+        assert!(!rules.is_set_won(4, 15, 14));
+        assert!(!rules.is_set_won(3, 15, 13));
+    }
+
+    #[test]
+    fn cap_wins_regardless_of_margin() {
+        let rules = MatchRules {
+            points_per_set: 21,
+            point_cap: Some(23),
+            ..MatchRules::default()
+        };
+
+        assert!(rules.is_set_won(0, 21, 19));
+        assert!(!rules.is_set_won(0, 22, 21));
+        assert!(rules.is_set_won(0, 23, 22));
+    }
+}
@@ -1,16 +1,22 @@
 use std::collections::HashMap;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::parser::parser::{Action, ActionType};
+use crate::lineup::{Lineup, Substitutions};
+use crate::match_rules::{Discipline, MatchRules};
+use crate::parser::error::Reason;
+use crate::parser::parser::{Action, ActionType, Height, Rally};
+use crate::stats::attack::{Attack, AttackStats};
+use crate::stats::setting::{SetAction, SettingStats};
 
-#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Team {
     Away,
     Home,
 }
 
-#[derive(Clone, Serialize, Deserialize, Copy, Hash, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Player(pub u8);
 
 #[derive(Clone, Serialize, Deserialize, Copy)]
@@ -30,6 +36,27 @@ impl PlayerScores {
         }
     }
 
+    pub fn attempt(self) -> Self {
+        Self {
+            all: self.all + 1,
+            ..self
+        }
+    }
+
+    pub fn score(self) -> Self {
+        Self {
+            scored: self.scored + 1,
+            ..self
+        }
+    }
+
+    pub fn fault(self) -> Self {
+        Self {
+            faults: self.faults + 1,
+            ..self
+        }
+    }
+
     pub fn merge(self, other: Self) -> Self {
         Self {
             scored: self.scored + other.scored,
@@ -46,6 +73,12 @@ pub struct PlayerStats {
     pub hits: PlayerScores,
     pub blocks: PlayerScores,
     pub serves: PlayerScores,
+    pub receives: PlayerScores,
+    pub passes: PlayerScores,
+    pub sets: PlayerScores,
+    pub freeballs: PlayerScores,
+    pub attack: AttackStats,
+    pub setting: SettingStats,
 }
 
 impl PlayerStats {
@@ -55,15 +88,96 @@ impl PlayerStats {
             hits: PlayerScores::new(),
             blocks: PlayerScores::new(),
             serves: PlayerScores::new(),
+            receives: PlayerScores::new(),
+            passes: PlayerScores::new(),
+            sets: PlayerScores::new(),
+            freeballs: PlayerScores::new(),
+            attack: AttackStats::new(),
+            setting: SettingStats::new(),
+        }
+    }
+
+    /// Applies `update` to the scores of the skill `action_type` belongs to.
+    fn update_skill(
+        self,
+        action_type: ActionType,
+        update: impl FnOnce(PlayerScores) -> PlayerScores,
+    ) -> Self {
+        match action_type {
+            ActionType::Serve(_, _) => Self {
+                serves: update(self.serves),
+                ..self
+            },
+            ActionType::Receive(_, _) => Self {
+                receives: update(self.receives),
+                ..self
+            },
+            ActionType::Pass(_, _) => Self {
+                passes: update(self.passes),
+                ..self
+            },
+            ActionType::Set => Self {
+                sets: update(self.sets),
+                ..self
+            },
+            ActionType::Hit(_, _) => Self {
+                hits: update(self.hits),
+                ..self
+            },
+            ActionType::Block(_, _) => Self {
+                blocks: update(self.blocks),
+                ..self
+            },
+            ActionType::Freeball(_) => Self {
+                freeballs: update(self.freeballs),
+                ..self
+            },
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            player: self.player,
+            hits: self.hits.merge(other.hits),
+            blocks: self.blocks.merge(other.blocks),
+            serves: self.serves.merge(other.serves),
+            receives: self.receives.merge(other.receives),
+            passes: self.passes.merge(other.passes),
+            sets: self.sets.merge(other.sets),
+            freeballs: self.freeballs.merge(other.freeballs),
+            attack: self.attack.merge(other.attack),
+            setting: self.setting.merge(other.setting),
         }
     }
 
     fn scored(self) -> i16 {
-        self.hits.scored + self.blocks.scored + self.serves.scored
+        [
+            self.hits,
+            self.blocks,
+            self.serves,
+            self.receives,
+            self.passes,
+            self.sets,
+            self.freeballs,
+        ]
+        .iter()
+        .map(|scores| scores.scored)
+        .sum()
     }
 
     fn faulted(self) -> i16 {
-        self.hits.faults + self.blocks.faults + self.serves.faults
+        [
+            self.hits,
+            self.blocks,
+            self.serves,
+            self.receives,
+            self.passes,
+            self.sets,
+            self.freeballs,
+        ]
+        .iter()
+        .map(|scores| scores.faults)
+        .sum()
     }
 }
 
@@ -71,27 +185,14 @@ impl PlayerStats {
 pub struct StatsByPlayer(pub HashMap<Player, PlayerStats>);
 
 impl StatsByPlayer {
+    /// Adds `other` on top, players seen for the first time are inserted.
     fn merge(&self, other: Self) -> StatsByPlayer {
-        let new_map: HashMap<Player, PlayerStats> = self
-            .0
-            .clone()
-            .into_iter()
-            .map(|(player, stats)| {
-                let player_update = other.0.get(&player);
-
-                let new_stats = match player_update {
-                    None => stats,
-                    Some(player_update) => PlayerStats {
-                        player,
-                        hits: stats.hits.merge(player_update.hits),
-                        blocks: stats.blocks.merge(player_update.blocks),
-                        serves: stats.serves.merge(player_update.serves),
-                    },
-                };
-
-                (player, new_stats)
-            })
-            .collect();
+        let mut new_map = self.0.clone();
+
+        other.0.into_iter().for_each(|(player, update)| {
+            let stats = new_map.entry(player).or_insert(PlayerStats::new(player));
+            *stats = stats.merge(update);
+        });
 
         StatsByPlayer(new_map)
     }
@@ -102,39 +203,135 @@ impl StatsByPlayer {
 struct TeamStats {
     sets: u8,
     points: u8,
+    players: Vec<Player>,
+    liberos: Vec<Player>,
+    /// Only known once the lineup for the current set was entered.
+    lineup: Option<Lineup>,
+    substitutions: Substitutions,
+    /// Players taken out through an exceptional substitution can't play again.
+    injured: Vec<Player>,
     player_stats: StatsByPlayer,
 }
 
+impl TeamStats {
+    fn new() -> Self {
+        Self {
+            sets: 0,
+            points: 0,
+            players: Vec::new(),
+            liberos: Vec::new(),
+            lineup: None,
+            substitutions: Substitutions::new(),
+            injured: Vec::new(),
+            player_stats: StatsByPlayer(HashMap::new()),
+        }
+    }
+
+    fn add_players(&mut self, players: &[Player]) {
+        players.iter().for_each(|player| {
+            if !self.players.contains(player) {
+                self.players.push(*player)
+            }
+        });
+    }
+}
+
 #[derive(Serialize, Clone)]
 pub struct UpdateMatchState {
+    #[serde(skip)]
+    pub actions: Vec<Action>,
     pub point_to: Team,
+    pub serving_team: Option<Team>,
+    pub server: Option<Player>,
+    pub away_players: Vec<Player>,
+    pub home_players: Vec<Player>,
     pub away_player_stats: StatsByPlayer,
     pub home_player_stats: StatsByPlayer,
+    /// Unix time in seconds the rally was recorded at, if known.
+    pub at: Option<u64>,
 }
 
 impl UpdateMatchState {
     // TODO: Move this logic/type to remove cyclical dependency
-    pub fn new(actions: Vec<Action>) -> UpdateMatchState {
+    pub fn new(rally: Rally) -> UpdateMatchState {
         let mut stats: HashMap<Team, HashMap<Player, PlayerStats>> = HashMap::new();
 
-        actions.clone().into_iter().for_each(|action| {
-            let mut default_stats = PlayerStats::new(action.player);
+        let players_of = |team: Team| {
+            rally
+                .actions
+                .iter()
+                .filter(|action| action.team == team)
+                .map(|action| action.player)
+                .unique()
+                .collect()
+        };
+        let away_players = players_of(Team::Away);
+        let home_players = players_of(Team::Home);
 
+        let mut credit = |team: Team,
+                          player: Player,
+                          action_type: ActionType,
+                          update: fn(PlayerScores) -> PlayerScores| {
             let player_stats = stats
-                .get_mut(&action.team)
-                .and_then(|m| m.get_mut(&action.player))
-                .unwrap_or(&mut default_stats);
+                .entry(team)
+                .or_default()
+                .entry(player)
+                .or_insert(PlayerStats::new(player));
+            *player_stats = player_stats.update_skill(action_type, update);
+        };
 
-            match action.action_type {
-                ActionType::Serve(_, _) => player_stats.serves.all += 1,
-                ActionType::Hit(_) => player_stats.hits.all += 1,
-                ActionType::Block(_, _) => player_stats.blocks.all += 1,
-                _ => (),
-            }
+        rally.actions.iter().for_each(|action| {
+            credit(
+                action.team,
+                action.player,
+                action.action_type,
+                PlayerScores::attempt,
+            )
+        });
+
+        if let Some(scored) = rally.who.scored {
+            credit(
+                scored.team,
+                scored.player,
+                scored.action_type,
+                PlayerScores::score,
+            );
+        }
+
+        if let Some(faulted) = rally.who.faulted {
+            credit(
+                faulted.team,
+                faulted.player,
+                faulted.action_type,
+                PlayerScores::fault,
+            );
+        }
+
+        Attack::all(&rally).iter().for_each(|attack| {
+            let player_stats = stats
+                .entry(attack.team)
+                .or_default()
+                .entry(attack.player)
+                .or_insert(PlayerStats::new(attack.player));
+            player_stats.attack = player_stats.attack.record_attack(attack);
+        });
+
+        SetAction::all(&rally).iter().for_each(|set| {
+            let player_stats = stats
+                .entry(set.team)
+                .or_default()
+                .entry(set.setter)
+                .or_insert(PlayerStats::new(set.setter));
+            player_stats.setting = player_stats.setting.record(set);
         });
 
         UpdateMatchState {
-            point_to: actions.last().and_then(|a| a.point_to).expect("Missing actions or point_to!"),
+            actions: rally.actions.clone(),
+            point_to: rally.who.point_to,
+            serving_team: rally.serve().map(|serve| serve.team),
+            server: rally.serve().map(|serve| serve.player),
+            away_players,
+            home_players,
             away_player_stats: stats
                 .get(&Team::Away)
                 .map(|stats| StatsByPlayer(stats.clone()))
@@ -143,10 +340,39 @@ impl UpdateMatchState {
                 .get(&Team::Home)
                 .map(|stats| StatsByPlayer(stats.clone()))
                 .unwrap_or(StatsByPlayer(HashMap::new())),
+            at: None,
         }
     }
 }
 
+/// The score at which something happened during a set.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreMoment {
+    pub set: u8,
+    pub away_points: u8,
+    pub home_points: u8,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeout {
+    pub team: Team,
+    pub moment: ScoreMoment,
+}
+
+/// Final score of a completed set. The duration is measured between the first
+/// and the last rally of the set and only known if both were timestamped.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SetScore {
+    pub set: u8,
+    pub away_points: u8,
+    pub home_points: u8,
+    pub winner: Team,
+    pub duration_secs: Option<u64>,
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 enum MatchStatus {
     InProgress,
@@ -156,12 +382,145 @@ enum MatchStatus {
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct MatchState {
+    rules: MatchRules,
     away_team: TeamStats,
     home_team: TeamStats,
     status: MatchStatus,
+    /// `None` before the first rally of a set.
+    serving_team: Option<Team>,
+    /// Only known while the serving team keeps the serve.
+    server: Option<Player>,
+    side_switches: Vec<ScoreMoment>,
+    timeouts: Vec<Timeout>,
+    technical_timeouts: Vec<ScoreMoment>,
+    set_scores: Vec<SetScore>,
+    /// When the first rally of the current set was recorded.
+    set_started_at: Option<u64>,
 }
 
 impl MatchState {
+    pub fn new(rules: MatchRules) -> Self {
+        Self {
+            rules,
+            away_team: TeamStats::new(),
+            home_team: TeamStats::new(),
+            status: MatchStatus::InProgress,
+            serving_team: None,
+            server: None,
+            side_switches: Vec::new(),
+            timeouts: Vec::new(),
+            technical_timeouts: Vec::new(),
+            set_scores: Vec::new(),
+            set_started_at: None,
+        }
+    }
+
+    pub fn current_set(&self) -> u8 {
+        self.sets_played() + 1
+    }
+
+    /// Rotation of the team, only known once its lineup was entered.
+    pub fn rotation(&self, team: Team) -> Option<u8> {
+        self.team(team).lineup.map(|lineup| lineup.rotation)
+    }
+
+    pub fn is_libero(&self, team: Team, player: Player) -> bool {
+        self.team(team).liberos.contains(&player)
+    }
+
+    fn team(&self, team: Team) -> &TeamStats {
+        match team {
+            Team::Away => &self.away_team,
+            Team::Home => &self.home_team,
+        }
+    }
+
+    fn team_mut(&mut self, team: Team) -> &mut TeamStats {
+        match team {
+            Team::Away => &mut self.away_team,
+            Team::Home => &mut self.home_team,
+        }
+    }
+
+    fn sets_played(&self) -> u8 {
+        self.away_team.sets + self.home_team.sets
+    }
+
+    /// Points played in the current set, wider than a team's points so a long
+    /// deuce can't overflow it.
+    fn total_points(&self) -> u16 {
+        u16::from(self.away_team.points) + u16::from(self.home_team.points)
+    }
+
+    pub fn timeouts(&self) -> &[Timeout] {
+        &self.timeouts
+    }
+
+    pub fn technical_timeouts(&self) -> &[ScoreMoment] {
+        &self.technical_timeouts
+    }
+
+    pub fn score_moment(&self) -> ScoreMoment {
+        ScoreMoment {
+            set: self.current_set(),
+            away_points: self.away_team.points,
+            home_points: self.home_team.points,
+        }
+    }
+
+    /// Records the side switches and technical timeouts due at the current score.
+    fn record_breaks(&mut self, point_to: Team) {
+        let deciding = self.rules.is_deciding_set(self.sets_played());
+        let points = self.team(point_to).points;
+        let opponent_points = self.team(point_to.get_opponent()).points;
+
+        if !deciding && points > opponent_points && self.rules.technical_timeouts.contains(&points)
+        {
+            self.technical_timeouts.push(self.score_moment());
+        }
+
+        let Discipline::Beach(beach) = self.rules.discipline else {
+            return;
+        };
+
+        let total_points = self.total_points();
+        let switch_every = match deciding {
+            true => beach.deciding_side_switch_every,
+            false => beach.side_switch_every,
+        };
+
+        if total_points.is_multiple_of(u16::from(switch_every)) {
+            self.side_switches.push(self.score_moment());
+        }
+
+        if !deciding && beach.technical_timeout_at.map(u16::from) == Some(total_points) {
+            self.technical_timeouts.push(self.score_moment());
+        }
+    }
+
+    pub fn points(&self, team: Team) -> u8 {
+        self.team(team).points
+    }
+
+    /// Points needed to win the current set, ignoring the margin.
+    pub fn set_points(&self) -> u8 {
+        self.rules.set_points(self.sets_played())
+    }
+
+    /// Whether winning the next rally wins `team` the set.
+    pub fn is_set_point(&self, team: Team) -> bool {
+        self.rules.is_set_won(
+            self.sets_played(),
+            self.points(team).saturating_add(1),
+            self.points(team.get_opponent()),
+        )
+    }
+
+    /// Whether winning the next rally wins `team` the match.
+    pub fn is_match_point(&self, team: Team) -> bool {
+        self.is_set_point(team) && self.team(team).sets + 1 == self.rules.sets_to_win
+    }
+
     fn get_set_winner(&self) -> Option<Team> {
         let (winning_team, losing_team, team) = if self.away_team.points > self.home_team.points {
             (&self.away_team, &self.home_team, Team::Away)
@@ -169,42 +528,280 @@ impl MatchState {
             (&self.home_team, &self.away_team, Team::Home)
         };
 
-        let set_point_cieling = match winning_team.sets + losing_team.sets == 5 {
-            true => 15,
-            false => 25,
-        };
+        match self
+            .rules
+            .is_set_won(self.sets_played(), winning_team.points, losing_team.points)
+        {
+            true => Some(team),
+            false => None,
+        }
+    }
+
+    /// Nothing can happen on court once a team has won the match.
+    fn ensure_in_progress(&self) -> Result<(), Reason> {
+        match self.status {
+            MatchStatus::InProgress => Ok(()),
+            MatchStatus::Finished => Err(Reason::match_finished()),
+        }
+    }
 
-        match (winning_team.points, losing_team.points) {
-            (wp, lp) if wp >= set_point_cieling && wp - lp > 1 => Some(team),
-            (_, _) => None,
+    pub fn set_lineup(self, team: Team, lineup: Lineup) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        if let Discipline::Beach(_) = self.rules.discipline {
+            return Err(Reason::no_lineups_in_beach());
+        }
+
+        if self.total_points() > 0 {
+            return Err(Reason::lineup_after_set_start());
+        }
+
+        let has_libero = lineup
+            .positions
+            .iter()
+            .any(|player| self.is_libero(team, *player));
+
+        if has_libero {
+            return Err(Reason::libero_in_lineup());
+        }
+
+        let mut new_state = self.clone();
+        let team_stats = new_state.team_mut(team);
+        team_stats.add_players(&lineup.positions);
+        team_stats.lineup = Some(lineup);
+        team_stats.substitutions = Substitutions::new();
+
+        Ok(new_state)
+    }
+
+    pub fn designate_libero(self, team: Team, libero: Player) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        if let Discipline::Beach(_) = self.rules.discipline {
+            return Err(Reason::no_liberos_in_beach());
+        }
+
+        let team_stats = self.team(team);
+        let on_court = team_stats
+            .lineup
+            .is_some_and(|lineup| lineup.is_on_court(libero));
+
+        if on_court || team_stats.liberos.contains(&libero) {
+            return Err(Reason::invalid_libero());
+        }
+
+        if team_stats.liberos.len() >= 2 {
+            return Err(Reason::too_many_liberos());
+        }
+
+        let mut new_state = self.clone();
+        let team_stats = new_state.team_mut(team);
+        team_stats.liberos.push(libero);
+        team_stats.add_players(&[libero]);
+
+        Ok(new_state)
+    }
+
+    pub fn swap_libero(
+        self,
+        team: Team,
+        libero: Player,
+        player: Player,
+    ) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        if !self.is_libero(team, libero) {
+            return Err(Reason::not_a_libero());
+        }
+
+        let libero_can_serve = self.rules.libero_can_serve;
+        let mut new_state = self.clone();
+        let team_stats = new_state.team_mut(team);
+        let lineup = team_stats.lineup.ok_or(Reason::no_lineup())?;
+
+        team_stats.lineup = Some(lineup.swap_libero(libero, player, libero_can_serve)?);
+
+        Ok(new_state)
+    }
+
+    /// Liberos may not block, attack above the height of the net or serve
+    /// unless the rules allow it. Only hits marked high are known to be above
+    /// the net, any other hit may have been played below it.
+    fn validate_libero_actions(&self, actions: &[Action]) -> Result<(), Reason> {
+        let illegal = actions.iter().position(|action| {
+            let is_libero = self.is_libero(action.team, action.player);
+
+            match action.action_type {
+                ActionType::Serve(_, _) => is_libero && !self.rules.libero_can_serve,
+                ActionType::Hit(Some(Height::High), _) | ActionType::Block(_, _) => is_libero,
+                _ => false,
+            }
+        });
+
+        match illegal {
+            Some(idx) => Err(Reason::illegal_libero_action().with_location(idx)),
+            None => Ok(()),
         }
     }
 
-    pub fn update(self, update: UpdateMatchState) -> MatchState {
+    /// An exceptional substitution replaces an injured player with anyone who
+    /// isn't on court, it doesn't count towards the limit but the injured
+    /// player can't come back.
+    pub fn substitute(
+        self,
+        team: Team,
+        player_out: Player,
+        player_in: Player,
+        exceptional: bool,
+    ) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        let max_substitutions = self.rules.max_substitutions;
         let mut new_state = self.clone();
+        let team_stats = new_state.team_mut(team);
+        let lineup = team_stats.lineup.ok_or(Reason::no_lineup())?;
+
+        if team_stats.liberos.contains(&player_in) || team_stats.liberos.contains(&player_out) {
+            return Err(Reason::libero_substitution());
+        }
+
+        if team_stats.injured.contains(&player_in) {
+            return Err(Reason::injured_player());
+        }
+
+        team_stats.lineup = Some(lineup.substitute(player_out, player_in)?);
+        team_stats.add_players(&[player_in]);
+
+        match exceptional {
+            true => team_stats.injured.push(player_out),
+            false => {
+                team_stats.substitutions = team_stats.substitutions.clone().record(
+                    player_out,
+                    player_in,
+                    max_substitutions,
+                )?
+            }
+        }
+
+        Ok(new_state)
+    }
+
+    pub fn call_timeout(self, team: Team) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        let moment = self.score_moment();
+        let taken = self
+            .timeouts
+            .iter()
+            .filter(|timeout| timeout.team == team && timeout.moment.set == moment.set)
+            .count();
+
+        if taken >= usize::from(self.rules.timeouts_per_set) {
+            return Err(Reason::no_timeouts_left());
+        }
+
+        let mut new_state = self.clone();
+        new_state.timeouts.push(Timeout { team, moment });
+
+        Ok(new_state)
+    }
 
-        match update.point_to {
-            Team::Away => new_state.away_team.points += 1,
-            Team::Home => new_state.home_team.points += 1,
+    pub fn update(self, update: UpdateMatchState) -> Result<MatchState, Reason> {
+        self.ensure_in_progress()?;
+
+        let mut new_state = self.clone();
+
+        new_state.away_team.add_players(&update.away_players);
+        new_state.home_team.add_players(&update.home_players);
+
+        if let Discipline::Beach(_) = self.rules.discipline {
+            if new_state.away_team.players.len() > 2 || new_state.home_team.players.len() > 2 {
+                return Err(Reason::too_many_beach_players());
+            }
+        }
+
+        self.validate_libero_actions(&update.actions)?;
+
+        if let (Some(expected), Some(actual)) = (self.serving_team, update.serving_team) {
+            if expected != actual {
+                return Err(Reason::wrong_serving_team());
+            }
+        }
+
+        if let (Some(serving_team), Some(server)) = (update.serving_team, update.server) {
+            let expected_server = self.team(serving_team).lineup.map(|l| l.server());
+
+            if expected_server.is_some_and(|expected| expected != server) {
+                return Err(Reason::rotation_fault());
+            }
+        }
+
+        if self.total_points() == 0 {
+            new_state.set_started_at = update.at;
+        }
+
+        let team_stats = new_state.team_mut(update.point_to);
+        team_stats.points = team_stats
+            .points
+            .checked_add(1)
+            .ok_or(Reason::too_many_points())?;
+
+        // The team winning a side-out rotates before it serves
+        let side_out = update.serving_team != Some(update.point_to);
+
+        if side_out {
+            team_stats.lineup = team_stats.lineup.map(|lineup| lineup.rotate());
+        }
+
+        let server = match side_out {
+            true => team_stats.lineup.map(|lineup| lineup.server()),
+            false => update.server,
         };
 
-        new_state
-            .get_set_winner()
-            .into_iter()
-            .for_each(|t| match t {
-                Team::Away => {
-                    new_state.away_team.points = 0;
-                    new_state.home_team.points = 0;
-                    new_state.away_team.sets += 1;
-                }
-                Team::Home => {
-                    new_state.home_team.points = 0;
-                    new_state.away_team.points = 0;
-                    new_state.home_team.sets += 1;
-                }
-            });
-
-        if new_state.home_team.sets == 3 || new_state.away_team.sets == 3 {
+        new_state.serving_team = Some(update.point_to);
+        new_state.server = server;
+
+        let set_winner = new_state.get_set_winner();
+
+        match set_winner {
+            None => new_state.record_breaks(update.point_to),
+            Some(winner) => {
+                new_state.set_scores.push(SetScore {
+                    set: self.current_set(),
+                    away_points: new_state.away_team.points,
+                    home_points: new_state.home_team.points,
+                    winner,
+                    duration_secs: new_state
+                        .set_started_at
+                        .zip(update.at)
+                        .map(|(start, end)| end.saturating_sub(start)),
+                });
+                new_state.set_started_at = None;
+                new_state.serving_team = None;
+                new_state.server = None;
+                new_state.away_team.lineup = None;
+                new_state.home_team.lineup = None;
+                new_state.away_team.substitutions = Substitutions::new();
+                new_state.home_team.substitutions = Substitutions::new();
+            }
+        }
+
+        set_winner.into_iter().for_each(|t| match t {
+            Team::Away => {
+                new_state.away_team.points = 0;
+                new_state.home_team.points = 0;
+                new_state.away_team.sets += 1;
+            }
+            Team::Home => {
+                new_state.home_team.points = 0;
+                new_state.away_team.points = 0;
+                new_state.home_team.sets += 1;
+            }
+        });
+
+        if new_state.home_team.sets == self.rules.sets_to_win
+            || new_state.away_team.sets == self.rules.sets_to_win
+        {
             new_state.status = MatchStatus::Finished;
         }
 
@@ -217,6 +814,131 @@ impl MatchState {
             .player_stats
             .merge(update.home_player_stats);
 
-        new_state
+        Ok(new_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+    use crate::Config;
+
+    fn play(state: MatchState, rally: &str) -> Result<MatchState, Reason> {
+        let rally = parse(Config::default(), rally)?;
+        state.update(UpdateMatchState::new(rally))
+    }
+
+    #[test]
+    fn nothing_happens_after_the_match_is_won() {
+        let rules = MatchRules {
+            sets_to_win: 1,
+            deciding_set_points: 2,
+            ..MatchRules::default()
+        };
+        let state = play(MatchState::new(rules), "@1S5").unwrap();
+        let state = play(state, "@1S5").unwrap();
+
+        assert_eq!(state.away_team.sets, 1);
+        assert!(matches!(state.status, MatchStatus::Finished));
+        assert!(play(state.clone(), "@1S5").is_err());
+        assert!(play(state.clone(), "!1S5").is_err());
+        assert!(state.clone().call_timeout(Team::Home).is_err());
+        assert!(state
+            .clone()
+            .designate_libero(Team::Home, Player(7))
+            .is_err());
+        let lineup = Lineup::new([1, 2, 3, 4, 5, 6].map(Player)).unwrap();
+        assert!(state.set_lineup(Team::Home, lineup).is_err());
+    }
+
+    #[test]
+    fn points_are_credited_to_the_acting_teams() {
+        // Home's pass after its own reception fails, so Away wins the point
+        let state = play(MatchState::new(MatchRules::default()), "@6S5 !2R !3P").unwrap();
+        let home = &state.home_team.player_stats.0;
+
+        assert_eq!(state.away_team.points, 1);
+        assert!(!state.away_team.player_stats.0.contains_key(&Player(3)));
+        assert_eq!(home[&Player(3)].passes.faults, 1);
+        assert_eq!(home[&Player(2)].receives.scored, 0);
+
+        // An attack tooled off a receiver scores for the attacker
+        let state = play(state, "@6S5 !2R !3E !4H5 @1R").unwrap();
+        let home = &state.home_team.player_stats.0;
+
+        assert_eq!(state.home_team.points, 1);
+        assert_eq!(home[&Player(4)].hits.scored, 1);
+        assert_eq!(
+            state.away_team.player_stats.0[&Player(1)].receives.faults,
+            1
+        );
+    }
+
+    #[test]
+    fn liberos_only_attack_below_the_net() {
+        let state = MatchState::new(MatchRules::default())
+            .designate_libero(Team::Home, Player(7))
+            .unwrap();
+
+        let illegal = |rally: &str| {
+            play(state.clone(), rally)
+                .err()
+                .map(|reason| reason.error_msg)
+                == Some(Reason::illegal_libero_action().error_msg)
+        };
+
+        assert!(play(state.clone(), "@1S5 !7R !2E !7H5").is_ok());
+        assert!(play(state.clone(), "@1S5 !7R !2E !7HL5").is_ok());
+        assert!(illegal("@1S5 !7R !2E !7HH5"));
+        assert!(illegal("@1S5 !2R !3E !4H5 @2P !7B@"));
+    }
+
+    /// Plays rallies won alternately by each team, starting with Away, until
+    /// `total` points were played in the set.
+    fn alternate_points(rules: MatchRules, total: u16) -> MatchState {
+        (0..total).fold(MatchState::new(rules), |state, idx| {
+            let team = match idx % 2 {
+                0 => Team::Away,
+                _ => Team::Home,
+            };
+            // The winner of the previous rally serves, the team to score wins
+            // with an ace on its own serve or an error on the opponent's
+            let rally = match (state.serving_team, team) {
+                (None | Some(Team::Away), Team::Away) => "@1S5",
+                (Some(Team::Home), Team::Home) => "!1S5",
+                (_, Team::Away) => "!1S0",
+                (_, Team::Home) => "@1S0",
+            };
+
+            play(state, rally).unwrap()
+        })
+    }
+
+    fn totals(moments: &[ScoreMoment]) -> Vec<u16> {
+        moments
+            .iter()
+            .map(|moment| u16::from(moment.away_points) + u16::from(moment.home_points))
+            .collect()
+    }
+
+    #[test]
+    fn beach_sides_switch_every_seven_points_with_a_technical_timeout_at_21() {
+        let state = alternate_points(MatchRules::beach(), 22);
+
+        assert_eq!(totals(&state.side_switches), vec![7, 14, 21]);
+        assert_eq!(totals(&state.technical_timeouts), vec![21]);
+    }
+
+    #[test]
+    fn beach_deciding_set_switches_every_five_points_without_technical_timeout() {
+        let rules = MatchRules {
+            sets_to_win: 1,
+            ..MatchRules::beach()
+        };
+        let state = alternate_points(rules, 22);
+
+        assert_eq!(totals(&state.side_switches), vec![5, 10, 15, 20]);
+        assert!(state.technical_timeouts.is_empty());
     }
 }
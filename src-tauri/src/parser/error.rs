@@ -2,7 +2,7 @@ use serde::Serialize;
 
 type Location = usize;
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Reason {
     pub error_msg: &'static str,
@@ -67,4 +67,228 @@ impl Reason {
             location: 0
         }
     }
+
+    pub fn nothing_to_undo() -> Reason {
+        Reason {
+            error_msg: "There are no rallies left to undo.",
+            location: 0
+        }
+    }
+
+    pub fn nothing_to_redo() -> Reason {
+        Reason {
+            error_msg: "There are no undone rallies to redo.",
+            location: 0
+        }
+    }
+
+    pub fn no_such_event() -> Reason {
+        Reason {
+            error_msg: "The match history doesn't go that far.",
+            location: 0
+        }
+    }
+
+    pub fn match_unavailable() -> Reason {
+        Reason {
+            error_msg: "The current match couldn't be accessed, please restart the application.",
+            location: 0
+        }
+    }
+
+    pub fn invalid_rules() -> Reason {
+        Reason {
+            error_msg: "The match rules need at least 1 set and 1 point to win, a margin of at least 1, a cap that isn't below the set points and sides have to be switched after at least 1 point.",
+            location: 0
+        }
+    }
+
+    pub fn too_many_beach_players() -> Reason {
+        Reason {
+            error_msg: "A beach team only has 2 players, this rally uses a third player number.",
+            location: 0
+        }
+    }
+
+    pub fn not_a_beach_match() -> Reason {
+        Reason {
+            error_msg: "Beach statistics are only available for beach matches.",
+            location: 0
+        }
+    }
+
+    pub fn wrong_serving_team() -> Reason {
+        Reason {
+            error_msg: "The team that won the previous rally has to serve.",
+            location: 0
+        }
+    }
+
+    pub fn duplicate_lineup_player() -> Reason {
+        Reason {
+            error_msg: "A player can only be in one position of the lineup.",
+            location: 0
+        }
+    }
+
+    pub fn no_lineups_in_beach() -> Reason {
+        Reason {
+            error_msg: "Beach teams don't have a lineup.",
+            location: 0
+        }
+    }
+
+    pub fn lineup_after_set_start() -> Reason {
+        Reason {
+            error_msg: "The lineup can only be entered before the first rally of a set.",
+            location: 0
+        }
+    }
+
+    pub fn no_lineup() -> Reason {
+        Reason {
+            error_msg: "Enter the team's lineup for this set first.",
+            location: 0
+        }
+    }
+
+    pub fn substitute_already_on_court() -> Reason {
+        Reason {
+            error_msg: "The incoming player is already on court.",
+            location: 0
+        }
+    }
+
+    pub fn substituted_player_not_on_court() -> Reason {
+        Reason {
+            error_msg: "The outgoing player isn't on court.",
+            location: 0
+        }
+    }
+
+    pub fn rotation_fault() -> Reason {
+        Reason {
+            error_msg: "Rotation fault, this player isn't in the serving position.",
+            location: 0
+        }
+    }
+
+    pub fn event_kind_mismatch() -> Reason {
+        Reason {
+            error_msg: "An event can only be replaced by an event of the same kind.",
+            location: 0
+        }
+    }
+
+    pub fn no_liberos_in_beach() -> Reason {
+        Reason {
+            error_msg: "Beach teams don't have a libero.",
+            location: 0
+        }
+    }
+
+    pub fn invalid_libero() -> Reason {
+        Reason {
+            error_msg: "This player is already a libero or currently on court.",
+            location: 0
+        }
+    }
+
+    pub fn too_many_liberos() -> Reason {
+        Reason {
+            error_msg: "A team can have at most 2 liberos.",
+            location: 0
+        }
+    }
+
+    pub fn not_a_libero() -> Reason {
+        Reason {
+            error_msg: "This player isn't designated as a libero.",
+            location: 0
+        }
+    }
+
+    pub fn libero_in_lineup() -> Reason {
+        Reason {
+            error_msg: "A libero can't be part of the starting lineup.",
+            location: 0
+        }
+    }
+
+    pub fn libero_already_on_court() -> Reason {
+        Reason {
+            error_msg: "A libero is already on court, they have to swap back with the player they replaced.",
+            location: 0
+        }
+    }
+
+    pub fn libero_not_back_row() -> Reason {
+        Reason {
+            error_msg: "A libero can only replace a back row player.",
+            location: 0
+        }
+    }
+
+    pub fn libero_substitution() -> Reason {
+        Reason {
+            error_msg: "Liberos enter and leave the court through libero replacements, not substitutions.",
+            location: 0
+        }
+    }
+
+    pub fn illegal_libero_action() -> Reason {
+        Reason {
+            error_msg: "A libero can't attack above the net, block or serve.",
+            location: 0
+        }
+    }
+
+    pub fn too_many_substitutions() -> Reason {
+        Reason {
+            error_msg: "The team has used all of its substitutions in this set, only an exceptional substitution for an injury is possible.",
+            location: 0
+        }
+    }
+
+    pub fn illegal_substitution() -> Reason {
+        Reason {
+            error_msg: "A starting player can only come back once, in place of the player who replaced them.",
+            location: 0
+        }
+    }
+
+    pub fn injured_player() -> Reason {
+        Reason {
+            error_msg: "A player taken out through an exceptional substitution can't play again this match.",
+            location: 0
+        }
+    }
+
+    pub fn no_timeouts_left() -> Reason {
+        Reason {
+            error_msg: "The team has no timeouts left in this set.",
+            location: 0
+        }
+    }
+
+    pub fn too_many_points() -> Reason {
+        Reason {
+            error_msg: "The set can't go on for this many points.",
+            location: 0
+        }
+    }
+
+    pub fn no_heatmap_for_skill() -> Reason {
+        Reason {
+            error_msg: "There are only heatmaps for serves, attacks and receptions.",
+            location: 0
+        }
+    }
+
+    pub fn match_finished() -> Reason {
+        Reason {
+            error_msg: "The match is already finished.",
+            location: 0
+        }
+    }
 }
@@ -3,6 +3,7 @@ use std::str::Chars;
 
 use itertools::Itertools;
 use itertools::Position;
+use serde::Serialize;
 
 use crate::match_state::{Player, Team};
 use crate::parser::error::Reason;
@@ -27,11 +28,7 @@ impl Player {
             Some(second_digit) => first_digit * 10 + second_digit,
         };
 
-        Ok(Player(
-            number
-                .try_into()
-                .expect("This number should never be larger than an u8"),
-        ))
+        number.try_into().map(Player).map_err(|_| Reason::player())
     }
 }
 
@@ -46,7 +43,7 @@ impl Team {
         }
     }
 
-    fn get_opponent(self) -> Team {
+    pub fn get_opponent(self) -> Team {
         match self {
             Self::Away => Self::Home,
             Self::Home => Self::Away,
@@ -54,7 +51,7 @@ impl Team {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ServePosition {
     A,
     B,
@@ -87,6 +84,16 @@ pub enum SubZone {
 }
 
 impl SubZone {
+    /// 0 through 3 for subzones A through D.
+    pub fn index(self) -> usize {
+        match self {
+            SubZone::A => 0,
+            SubZone::B => 1,
+            SubZone::C => 2,
+            SubZone::D => 3,
+        }
+    }
+
     fn from_char(c: char) -> Result<Self, Reason> {
         match c {
             'A' => Ok(Self::A),
@@ -115,6 +122,37 @@ pub enum Zone {
 }
 
 impl Zone {
+    /// Court zone 1 through 9, `None` for the ones outside the court.
+    pub fn number(self) -> Option<u8> {
+        match self {
+            Zone::One(_) => Some(1),
+            Zone::Two(_) => Some(2),
+            Zone::Three(_) => Some(3),
+            Zone::Four(_) => Some(4),
+            Zone::Five(_) => Some(5),
+            Zone::Six(_) => Some(6),
+            Zone::Seven(_) => Some(7),
+            Zone::Eight(_) => Some(8),
+            Zone::Nine(_) => Some(9),
+            Zone::Overpass | Zone::OutOfBounds | Zone::Net => None,
+        }
+    }
+
+    pub fn sub_zone(self) -> Option<SubZone> {
+        match self {
+            Zone::One(sub_zone)
+            | Zone::Two(sub_zone)
+            | Zone::Three(sub_zone)
+            | Zone::Four(sub_zone)
+            | Zone::Five(sub_zone)
+            | Zone::Six(sub_zone)
+            | Zone::Seven(sub_zone)
+            | Zone::Eight(sub_zone)
+            | Zone::Nine(sub_zone) => sub_zone,
+            Zone::Overpass | Zone::OutOfBounds | Zone::Net => None,
+        }
+    }
+
     fn in_court(self) -> bool {
         match self {
             Zone::Overpass => false,
@@ -146,7 +184,7 @@ impl Zone {
 }
 
 #[derive(Clone, Copy)]
-enum Height {
+pub enum Height {
     Low,
     Mid,
     High,
@@ -176,64 +214,58 @@ pub enum ActionType {
     Receive(Option<Height>, Option<Zone>),
     Pass(Option<Height>, Option<Zone>),
     Set,
-    Hit(Option<Zone>),
+    /// A high hit was contacted entirely above the height of the net.
+    Hit(Option<Height>, Option<Zone>),
     Block(Team, Option<Zone>),
     Freeball(Option<Zone>),
 }
 
 #[derive(Clone, Copy)]
 pub struct Scored {
-    player: Player,
-    action_type: ActionType,
+    pub team: Team,
+    pub player: Player,
+    pub action_type: ActionType,
+}
+
+impl Scored {
+    fn new(action: Action) -> Self {
+        Self {
+            team: action.team,
+            player: action.player,
+            action_type: action.action_type,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
 pub struct WhoScored {
     pub scored: Option<Scored>,
     pub faulted: Option<Scored>,
-    point_to: Team,
+    pub point_to: Team,
 }
 
 impl WhoScored {
-    fn new_fault(player: Player, action_type: ActionType, point_to: Team) -> Self {
+    fn new_fault(faulted: Action) -> Self {
         Self {
             scored: None,
-            faulted: Some(Scored {
-                player,
-                action_type,
-            }),
-            point_to,
+            faulted: Some(Scored::new(faulted)),
+            point_to: faulted.team.get_opponent(),
         }
     }
 
-    fn new_scored(player: Player, action_type: ActionType, point_to: Team) -> Self {
+    fn new_scored(scored: Action) -> Self {
         Self {
-            scored: Some(Scored {
-                player,
-                action_type,
-            }),
+            scored: Some(Scored::new(scored)),
             faulted: None,
-            point_to,
+            point_to: scored.team,
         }
     }
 
-    fn new(
-        scored_player: Player,
-        scored_action_type: ActionType,
-        faulted_player: Player,
-        faulted_action_type: ActionType,
-        point_to: Team,
-    ) -> Self {
+    fn new(scored: Action, faulted: Action) -> Self {
         Self {
-            scored: Some(Scored {
-                player: scored_player,
-                action_type: scored_action_type,
-            }),
-            faulted: Some(Scored {
-                player: faulted_player,
-                action_type: faulted_action_type,
-            }),
-            point_to,
+            scored: Some(Scored::new(scored)),
+            faulted: Some(Scored::new(faulted)),
+            point_to: scored.team,
         }
     }
 }
@@ -244,6 +276,26 @@ pub struct Rally {
     pub who: WhoScored,
 }
 
+impl Rally {
+    /// The serve is always the first action of a parsed rally.
+    pub fn serve(&self) -> Option<&Action> {
+        self.actions.first()
+    }
+
+    /// Index of the action that lost the point, always one of the last two actions.
+    pub fn faulted_index(&self) -> Option<usize> {
+        let faulted = self.who.faulted?;
+
+        self.actions
+            .iter()
+            .enumerate()
+            .rev()
+            .take(2)
+            .find(|(_, a)| a.player == faulted.player && a.team == faulted.team)
+            .map(|(idx, _)| idx)
+    }
+}
+
 impl ActionType {
     fn parse_first(chars: &mut Peekable<Chars>) -> Result<ActionType, Reason> {
         chars
@@ -271,37 +323,16 @@ impl ActionType {
     }
 
     fn who_scored_point(action: Action, related_action: Option<Action>) -> WhoScored {
-        let player_faulted = WhoScored::new_fault(
-            action.player,
-            action.action_type,
-            action.team.get_opponent(),
-        );
-
-        let player_scored = WhoScored::new_scored(
-            action.player,
-            action.action_type,
-            action.team.get_opponent(),
-        );
-
-        let related_faulted = |related_action: Action| {
-            WhoScored::new(
-                action.player,
-                action.action_type,
-                related_action.player,
-                related_action.action_type,
-                action.team,
-            )
-        };
+        let player_faulted = WhoScored::new_fault(action);
 
-        let related_scored = |related_action: Action| {
-            WhoScored::new(
-                related_action.player,
-                related_action.action_type,
-                action.player,
-                action.action_type,
-                related_action.team,
-            )
-        };
+        let player_scored = WhoScored::new_scored(action);
+
+        // Only an action of the other team can have forced or caused the last one
+        let related_action = related_action.filter(|related| related.team != action.team);
+
+        let related_faulted = |related_action: Action| WhoScored::new(action, related_action);
+
+        let related_scored = |related_action: Action| WhoScored::new(related_action, action);
 
         match action.action_type {
             ActionType::Serve(_, Some(Zone::OutOfBounds | Zone::Net)) => player_faulted,
@@ -323,8 +354,8 @@ impl ActionType {
             // of assuming setter fault on last action (might be an over-set)
             ActionType::Set => player_faulted,
 
-            ActionType::Hit(Some(Zone::OutOfBounds | Zone::Net)) => player_faulted,
-            ActionType::Hit(_) => player_scored,
+            ActionType::Hit(_, Some(Zone::OutOfBounds | Zone::Net)) => player_faulted,
+            ActionType::Hit(_, _) => player_scored,
             ActionType::Block(t, zone)
                 if t != action.team && zone.map_or(true, |z| z.in_court()) =>
             {
@@ -382,6 +413,11 @@ impl ActionType {
                 }
                 'E' => Ok(ActionType::Set),
                 'H' => {
+                    let height = chars
+                        .peek()
+                        .and_then(|c| Height::from_char(*c).ok())
+                        .utap_some(|| chars.next().unit());
+
                     let zone = chars
                         .next()
                         .map(|zone| {
@@ -390,7 +426,7 @@ impl ActionType {
                         })
                         .transpose()?;
 
-                    Ok(ActionType::Hit(zone))
+                    Ok(ActionType::Hit(height, zone))
                 }
                 'B' => {
                     let team = chars
@@ -464,3 +500,115 @@ pub fn parse(config: Config, rally: &str) -> Result<Rally, Reason> {
         Ok(Rally { actions: a, who: who_scored })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+    use crate::match_history::{MatchEvent, MatchHistory};
+    use crate::match_rules::MatchRules;
+
+    // Every part of an action is picked from mostly valid notation with a few
+    // invalid choices, and a multi-byte character to catch slicing on bytes.
+    const TEAMS: &[&str] = &["@", "!", "@", "!", "", "#"];
+    const PLAYERS: &[&str] = &["1", "2", "3", "4", "5", "6", "12", "0", "99", "300", ""];
+    const SERVES: &[&str] = &["S", "S", "S", "S", "R", ""];
+    const ACTIONS: &[&str] = &["S", "R", "P", "E", "H", "B@", "B!", "F", "", "X"];
+    const MODIFIERS: &[&str] = &["", "", "A", "F", "L", "M", "H"];
+    const ZONES: &[&str] = &["", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "N", "V", "é"];
+    const SUB_ZONES: &[&str] = &["", "", "A", "B", "C", "D", "Z"];
+    /// Characters for inputs that don't follow the notation at all.
+    const ALPHABET: &[char] = &[
+        '@', '!', '#', '0', '1', '2', '5', '9', 'S', 'R', 'P', 'E', 'H', 'B', 'F', 'A', 'L', 'M',
+        'N', 'V', 'C', 'D', 'a', 's', ' ', ' ', ' ', '\t', '\n', 'é', 'ß', '→', '😀', '\u{0}',
+    ];
+
+    /// A linear congruential generator, so a failure always reproduces with
+    /// the same input.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % bound
+        }
+
+        fn rally(&mut self) -> String {
+            (0..1 + self.below(6))
+                .map(|idx| {
+                    let actions = match idx {
+                        0 => SERVES,
+                        _ => ACTIONS,
+                    };
+
+                    [TEAMS, PLAYERS, actions, MODIFIERS, ZONES, SUB_ZONES]
+                        .iter()
+                        .map(|parts| parts[self.below(parts.len())])
+                        .collect::<String>()
+                })
+                .join(" ")
+        }
+
+        fn string(&mut self) -> String {
+            (0..self.below(24))
+                .map(|_| ALPHABET[self.below(ALPHABET.len())])
+                .collect()
+        }
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        let mut random = Random(39);
+
+        (0..200_000).map(|_| random.rally()).for_each(|rally| {
+            let result = catch_unwind(|| parse(Config::default(), &rally).is_ok());
+            assert!(result.is_ok(), "parse panicked on {rally:?}");
+        });
+    }
+
+    #[test]
+    fn parse_never_panics_on_arbitrary_strings() {
+        let mut random = Random(41);
+        let edge_cases = ["", " ", "  ", "\t", " @1S5", "@1S5 ", "@1S5  !2R", "@1S5\t!2R"];
+
+        edge_cases
+            .into_iter()
+            .map(String::from)
+            .chain((0..200_000).map(|_| random.string()))
+            .for_each(|input| {
+                let result = catch_unwind(|| parse(Config::default(), &input).is_ok());
+                assert!(result.is_ok(), "parse panicked on {input:?}");
+            });
+    }
+
+    #[test]
+    fn push_never_panics() {
+        let mut random = Random(40);
+        let mut history = MatchHistory::new(MatchRules::default());
+
+        (0..20_000)
+            .map(|_| random.rally())
+            .enumerate()
+            .for_each(|(idx, rally)| {
+                // Start over now and then so replaying the history stays cheap.
+                if idx % 200 == 0 {
+                    history = MatchHistory::new(MatchRules::default());
+                }
+
+                let pushed = catch_unwind(AssertUnwindSafe(|| {
+                    history
+                        .clone()
+                        .push(Config::default(), MatchEvent::Rally(rally.clone()))
+                }));
+                match pushed {
+                    Ok(Ok(pushed)) => history = pushed,
+                    Ok(Err(_)) => (),
+                    Err(_) => panic!("push panicked on {rally:?}"),
+                }
+            });
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Rally};
+use crate::stats::phase::Phase;
+
+/// A `Hit` and how it ended. Only the last attack of a rally can be a kill or
+/// an error, every other one was kept in play.
+#[derive(Clone, Copy)]
+pub struct Attack {
+    /// Index of the `Hit` in the rally's actions.
+    pub index: usize,
+    pub team: Team,
+    pub player: Player,
+    pub phase: Phase,
+    pub kill: bool,
+    pub error: bool,
+    pub blocked: bool,
+}
+
+impl Attack {
+    pub fn all(rally: &Rally) -> Vec<Attack> {
+        let is_hit = |action_type: ActionType| matches!(action_type, ActionType::Hit(_, _));
+        let phases = Phase::of_actions(rally);
+        let last_hit = rally
+            .actions
+            .iter()
+            .rposition(|action| is_hit(action.action_type));
+
+        rally
+            .actions
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| is_hit(action.action_type))
+            .map(|(idx, action)| {
+                let decisive = Some(idx) == last_hit;
+                let won = rally.who.point_to == action.team;
+                let kill = decisive
+                    && won
+                    && rally
+                        .who
+                        .scored
+                        .is_some_and(|s| s.player == action.player && is_hit(s.action_type));
+                let error = decisive
+                    && !won
+                    && rally
+                        .who
+                        .faulted
+                        .is_some_and(|f| f.player == action.player && is_hit(f.action_type));
+                let blocked = error
+                    && rally
+                        .who
+                        .scored
+                        .is_some_and(|s| matches!(s.action_type, ActionType::Block(_, _)));
+
+                Attack {
+                    index: idx,
+                    team: action.team,
+                    player: action.player,
+                    phase: phases[idx],
+                    kill,
+                    error,
+                    blocked,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Efficiency is (kills - errors) / attempts, blocked attacks count as errors.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AttackStats {
+    pub attempts: u16,
+    pub kills: u16,
+    pub errors: u16,
+    pub blocked: u16,
+    pub efficiency: Option<f32>,
+    pub kill_percentage: Option<f32>,
+}
+
+impl AttackStats {
+    pub fn new() -> Self {
+        Self {
+            attempts: 0,
+            kills: 0,
+            errors: 0,
+            blocked: 0,
+            efficiency: None,
+            kill_percentage: None,
+        }
+    }
+
+    fn from_counts(attempts: u16, kills: u16, errors: u16, blocked: u16) -> Self {
+        Self {
+            attempts,
+            kills,
+            errors,
+            blocked,
+            efficiency: match attempts {
+                0 => None,
+                attempts => Some((f32::from(kills) - f32::from(errors)) / f32::from(attempts)),
+            },
+            kill_percentage: match attempts {
+                0 => None,
+                attempts => Some(f32::from(kills) * 100.0 / f32::from(attempts)),
+            },
+        }
+    }
+
+    pub fn record_attack(self, attack: &Attack) -> Self {
+        Self::from_counts(
+            self.attempts + 1,
+            self.kills + u16::from(attack.kill),
+            self.errors + u16::from(attack.error),
+            self.blocked + u16::from(attack.blocked),
+        )
+    }
+
+    /// Counts the attacks `team` made during `rally`.
+    pub fn record(self, team: Team, rally: &Rally) -> Self {
+        Attack::all(rally)
+            .iter()
+            .filter(|attack| attack.team == team)
+            .fold(self, |stats, attack| stats.record_attack(attack))
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self::from_counts(
+            self.attempts + other.attempts,
+            self.kills + other.kills,
+            self.errors + other.errors,
+            self.blocked + other.blocked,
+        )
+    }
+}
+
+/// Attacks split by the phase of the rally they were played in, first-ball
+/// attacks are the side-out ones right off the serve reception.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseAttackStats {
+    pub total: AttackStats,
+    pub first_ball: AttackStats,
+    pub transition: AttackStats,
+    pub freeball: AttackStats,
+}
+
+impl PhaseAttackStats {
+    fn new() -> Self {
+        Self {
+            total: AttackStats::new(),
+            first_ball: AttackStats::new(),
+            transition: AttackStats::new(),
+            freeball: AttackStats::new(),
+        }
+    }
+
+    fn record(self, attack: &Attack) -> Self {
+        let total = self.total.record_attack(attack);
+
+        match attack.phase {
+            Phase::Serve => Self { total, ..self },
+            Phase::SideOut => Self {
+                total,
+                first_ball: self.first_ball.record_attack(attack),
+                ..self
+            },
+            Phase::Transition => Self {
+                total,
+                transition: self.transition.record_attack(attack),
+                ..self
+            },
+            Phase::Freeball => Self {
+                total,
+                freeball: self.freeball.record_attack(attack),
+                ..self
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerAttackStats {
+    pub team: Team,
+    pub player: Player,
+    pub stats: PhaseAttackStats,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAttackStats {
+    pub set: u8,
+    pub stats: PhaseAttackStats,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationAttackStats {
+    pub rotation: u8,
+    pub stats: PhaseAttackStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamAttackStats {
+    pub total: PhaseAttackStats,
+    pub by_set: Vec<SetAttackStats>,
+    /// Rallies played before the team's lineup was entered are left out.
+    pub by_rotation: Vec<RotationAttackStats>,
+}
+
+impl TeamAttackStats {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut total = PhaseAttackStats::new();
+        let mut by_set: BTreeMap<u8, PhaseAttackStats> = BTreeMap::new();
+        let mut by_rotation: BTreeMap<u8, PhaseAttackStats> = BTreeMap::new();
+
+        rallies.iter().for_each(|PlayedRally { before, rally }| {
+            Attack::all(rally)
+                .iter()
+                .filter(|attack| attack.team == team)
+                .for_each(|attack| {
+                    total = total.record(attack);
+
+                    let set_stats = by_set.entry(before.current_set()).or_default();
+                    *set_stats = set_stats.record(attack);
+
+                    if let Some(rotation) = before.rotation(team) {
+                        let rotation_stats = by_rotation.entry(rotation).or_default();
+                        *rotation_stats = rotation_stats.record(attack);
+                    }
+                });
+        });
+
+        TeamAttackStats {
+            total,
+            by_set: by_set
+                .into_iter()
+                .map(|(set, stats)| SetAttackStats { set, stats })
+                .collect(),
+            by_rotation: by_rotation
+                .into_iter()
+                .map(|(rotation, stats)| RotationAttackStats { rotation, stats })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttackReport {
+    pub players: Vec<PlayerAttackStats>,
+    pub away: TeamAttackStats,
+    pub home: TeamAttackStats,
+}
+
+impl AttackReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut players: HashMap<(Team, Player), PhaseAttackStats> = HashMap::new();
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            Attack::all(rally).iter().for_each(|attack| {
+                let stats = players.entry((attack.team, attack.player)).or_default();
+                *stats = stats.record(attack);
+            });
+        });
+
+        let mut players: Vec<PlayerAttackStats> = players
+            .into_iter()
+            .map(|((team, player), stats)| PlayerAttackStats {
+                team,
+                player,
+                stats,
+            })
+            .collect();
+        players.sort_by_key(|stats| (stats.team, stats.player));
+
+        AttackReport {
+            players,
+            away: TeamAttackStats::new(Team::Away, rallies),
+            home: TeamAttackStats::new(Team::Home, rallies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn blocks_and_tools_decide_the_last_attack() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &[
+                // Blocked back onto the attacker's side
+                "@1S5 !2R !3E !4H5 @7B!",
+                // Tooled off the block onto the blocker's side
+                "@1S5 !2R !3E !4H5 @7B@",
+                "!1S5 @2R @3E @4H0",
+                // Dug and killed in transition
+                "!1S5 @2R @3E @4H5 !5P !6E !7H5",
+            ],
+        );
+        let report = AttackReport::new(&rallies);
+        let player = |team: Team, number: u8| {
+            report
+                .players
+                .iter()
+                .find(|stats| stats.team == team && stats.player == Player(number))
+                .unwrap()
+                .stats
+        };
+
+        let home_hitter = player(Team::Home, 4).total;
+        assert_eq!(home_hitter.attempts, 2);
+        assert_eq!(home_hitter.kills, 1);
+        assert_eq!(home_hitter.errors, 1);
+        assert_eq!(home_hitter.blocked, 1);
+        assert_eq!(player(Team::Home, 4).first_ball.attempts, 2);
+
+        let away_hitter = player(Team::Away, 4).total;
+        assert_eq!(away_hitter.attempts, 2);
+        assert_eq!(away_hitter.errors, 1);
+        assert_eq!(away_hitter.blocked, 0);
+
+        let transition = player(Team::Home, 7).transition;
+        assert_eq!(transition.attempts, 1);
+        assert_eq!(transition.kills, 1);
+
+        assert_eq!(report.home.total.total.kills, 2);
+        assert_eq!(report.away.total.total.kills, 0);
+        assert_eq!(report.away.total.total.errors, 1);
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::ActionType;
+use crate::stats::Percentage;
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerSideOut {
+    pub player: Player,
+    pub side_out: Percentage,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeachStats {
+    pub away_side_out: Vec<PlayerSideOut>,
+    pub home_side_out: Vec<PlayerSideOut>,
+}
+
+impl BeachStats {
+    /// Side-out % of every player, counted over the rallies where they took
+    /// the serve receive.
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut side_outs: BTreeMap<(Team, Player), Percentage> = BTreeMap::new();
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            let receiver = rally
+                .actions
+                .iter()
+                .find(|action| matches!(action.action_type, ActionType::Receive(_, _)));
+
+            if let Some(receiver) = receiver {
+                let side_out = side_outs
+                    .entry((receiver.team, receiver.player))
+                    .or_default();
+
+                *side_out = side_out.record(rally.who.point_to == receiver.team);
+            }
+        });
+
+        let side_out_of = |team: Team| {
+            side_outs
+                .iter()
+                .filter(|((t, _), _)| *t == team)
+                .map(|((_, player), side_out)| PlayerSideOut {
+                    player: *player,
+                    side_out: *side_out,
+                })
+                .collect()
+        };
+
+        BeachStats {
+            away_side_out: side_out_of(Team::Away),
+            home_side_out: side_out_of(Team::Home),
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{MatchState, Player, PlayerScores, Team};
+use crate::parser::parser::ActionType;
+use crate::stats::attack::{Attack, AttackStats};
+use crate::stats::reception::{Reception, ReceptionStats};
+use crate::stats::serving::{PhaseStats, ServeStats};
+use crate::stats::Percentage;
+
+/// Points from the set target at which a set counts as late, e.g. 20+ in a set
+/// to 25 and 10+ in one to 15.
+const LATE_SET_MARGIN: u8 = 5;
+const TRAILING_BY: u8 = 3;
+
+/// A score situation seen from one team, a rally can be in several at once.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Situation {
+    /// Either team is close to the set target.
+    LateSet,
+    /// Either team is one point from winning the set.
+    SetPoint,
+    /// Either team is one point from winning the match.
+    MatchPoint,
+    Tied,
+    /// The team is behind by `TRAILING_BY` or more.
+    Trailing,
+}
+
+impl Situation {
+    const ALL: [Situation; 5] = [
+        Situation::LateSet,
+        Situation::SetPoint,
+        Situation::MatchPoint,
+        Situation::Tied,
+        Situation::Trailing,
+    ];
+
+    fn applies(self, before: &MatchState, team: Team) -> bool {
+        let points = before.points(team);
+        let opponent = team.get_opponent();
+        let opponent_points = before.points(opponent);
+
+        match self {
+            Situation::LateSet => {
+                points.max(opponent_points).saturating_add(LATE_SET_MARGIN) >= before.set_points()
+            }
+            Situation::SetPoint => before.is_set_point(team) || before.is_set_point(opponent),
+            Situation::MatchPoint => before.is_match_point(team) || before.is_match_point(opponent),
+            Situation::Tied => points == opponent_points,
+            Situation::Trailing => opponent_points.saturating_sub(points) >= TRAILING_BY,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerClutchStats {
+    pub player: Player,
+    pub points_scored: u16,
+    pub errors: u16,
+    pub serves: ServeStats,
+    pub reception: ReceptionStats,
+    pub attack: AttackStats,
+    pub blocks: PlayerScores,
+}
+
+impl PlayerClutchStats {
+    fn new(player: Player) -> Self {
+        Self {
+            player,
+            points_scored: 0,
+            errors: 0,
+            serves: ServeStats::new(),
+            reception: ReceptionStats::new(),
+            attack: AttackStats::new(),
+            blocks: PlayerScores::new(),
+        }
+    }
+}
+
+fn player_stats(
+    players: &mut BTreeMap<Player, PlayerClutchStats>,
+    player: Player,
+) -> &mut PlayerClutchStats {
+    players
+        .entry(player)
+        .or_insert(PlayerClutchStats::new(player))
+}
+
+/// Stats of one team over the rallies that started in `situation`, recorded
+/// the same way as in the full match reports.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SituationStats {
+    pub situation: Situation,
+    pub rallies: Percentage,
+    pub phase: PhaseStats,
+    pub serves: ServeStats,
+    pub reception: ReceptionStats,
+    pub attack: AttackStats,
+    pub blocks: PlayerScores,
+    pub players: Vec<PlayerClutchStats>,
+}
+
+impl SituationStats {
+    fn new(situation: Situation, team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut won = Percentage::new();
+        let mut phase = PhaseStats::new();
+        let mut serves = ServeStats::new();
+        let mut reception = ReceptionStats::new();
+        let mut attack = AttackStats::new();
+        let mut blocks = PlayerScores::new();
+        let mut players: BTreeMap<Player, PlayerClutchStats> = BTreeMap::new();
+
+        rallies
+            .iter()
+            .filter(|PlayedRally { before, .. }| situation.applies(before, team))
+            .for_each(|PlayedRally { rally, .. }| {
+                let Some(serve) = rally.serve() else {
+                    return;
+                };
+                let point_to = rally.who.point_to;
+
+                won = won.record(point_to == team);
+                phase = phase.record(team, serve.team, point_to);
+
+                if serve.team == team {
+                    serves = serves.record(rally);
+                    let stats = player_stats(&mut players, serve.player);
+                    stats.serves = stats.serves.record(rally);
+                }
+
+                Reception::all(rally)
+                    .iter()
+                    .filter(|r| r.team == team)
+                    .for_each(|r| {
+                        reception = reception.record(r);
+                        if let Some(player) = r.player {
+                            let stats = player_stats(&mut players, player);
+                            stats.reception = stats.reception.record(r);
+                        }
+                    });
+
+                Attack::all(rally)
+                    .iter()
+                    .filter(|a| a.team == team)
+                    .for_each(|a| {
+                        attack = attack.record_attack(a);
+                        let stats = player_stats(&mut players, a.player);
+                        stats.attack = stats.attack.record_attack(a);
+                    });
+
+                let is_block =
+                    |action_type: ActionType| matches!(action_type, ActionType::Block(_, _));
+                let scored = rally
+                    .who
+                    .scored
+                    .filter(|s| s.team == team && is_block(s.action_type));
+                let faulted = rally
+                    .who
+                    .faulted
+                    .filter(|f| f.team == team && is_block(f.action_type));
+                rally
+                    .actions
+                    .iter()
+                    .filter(|action| action.team == team && is_block(action.action_type))
+                    .for_each(|action| {
+                        let stats = player_stats(&mut players, action.player);
+                        blocks = blocks.attempt();
+                        stats.blocks = stats.blocks.attempt();
+                    });
+                if let Some(scored) = scored {
+                    blocks = blocks.score();
+                    let stats = player_stats(&mut players, scored.player);
+                    stats.blocks = stats.blocks.score();
+                }
+                if let Some(faulted) = faulted {
+                    blocks = blocks.fault();
+                    let stats = player_stats(&mut players, faulted.player);
+                    stats.blocks = stats.blocks.fault();
+                }
+
+                match (point_to == team, rally.who.scored, rally.who.faulted) {
+                    (true, Some(scored), _) => {
+                        player_stats(&mut players, scored.player).points_scored += 1
+                    }
+                    (false, _, Some(faulted)) => {
+                        player_stats(&mut players, faulted.player).errors += 1
+                    }
+                    _ => (),
+                }
+            });
+
+        SituationStats {
+            situation,
+            rallies: won,
+            phase,
+            serves,
+            reception,
+            attack,
+            blocks,
+            players: players.into_values().collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClutchReport {
+    pub away: Vec<SituationStats>,
+    pub home: Vec<SituationStats>,
+}
+
+impl ClutchReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let team_stats = |team: Team| {
+            Situation::ALL
+                .iter()
+                .map(|situation| SituationStats::new(*situation, team, rallies))
+                .collect()
+        };
+
+        Self {
+            away: team_stats(Team::Away),
+            home: team_stats(Team::Home),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    fn situation(stats: &[SituationStats], situation: Situation) -> &SituationStats {
+        stats.iter().find(|s| s.situation == situation).unwrap()
+    }
+
+    #[test]
+    fn rallies_are_split_by_the_score_they_started_at() {
+        let rules = MatchRules {
+            points_per_set: 6,
+            min_margin: 1,
+            ..MatchRules::default()
+        };
+        let rallies = play(
+            MatchHistory::new(rules),
+            &[
+                "@1S5",
+                "@1S5",
+                "@1S5",
+                "@1S5 !2R !3E !4H5",
+                "!1S0",
+                "@1S5 !2R !3E !4H5 @5B!",
+                "@1S5 !2R !3E !4H5",
+            ],
+        );
+        let report = ClutchReport::new(&rallies);
+
+        // Only the first rally started tied
+        let tied = situation(&report.away, Situation::Tied);
+        assert_eq!((tied.rallies.won, tied.rallies.all), (1, 1));
+        assert_eq!((tied.serves.attempts, tied.serves.aces), (1, 1));
+        let tied = situation(&report.home, Situation::Tied);
+        assert_eq!(tied.reception.contacts.attempts, 1);
+        assert_eq!(tied.reception.contacts.errors, 1);
+
+        // Down 0-3, 1-4 and 1-5
+        let trailing = situation(&report.home, Situation::Trailing);
+        assert_eq!((trailing.rallies.won, trailing.rallies.all), (2, 3));
+        assert_eq!(trailing.phase.side_out.all, 3);
+        assert_eq!(trailing.reception.positive.won, 3);
+        assert_eq!(trailing.attack.kills, 2);
+        assert_eq!(trailing.players.len(), 2);
+        assert_eq!(trailing.players[1].player.0, 4);
+        assert_eq!(trailing.players[1].points_scored, 2);
+        assert_eq!(trailing.players[1].errors, 1);
+        assert!(situation(&report.away, Situation::Trailing).rallies.all == 0);
+
+        // From 1-0 on
+        let late = situation(&report.away, Situation::LateSet);
+        assert_eq!(late.rallies.all, 6);
+        assert_eq!((late.blocks.all, late.blocks.scored), (1, 1));
+        let blocker = late.players.iter().find(|p| p.player.0 == 5).unwrap();
+        assert_eq!(blocker.blocks.scored, 1);
+        assert_eq!(late.serves.attempts, 5);
+
+        let set_point = situation(&report.away, Situation::SetPoint);
+        assert_eq!((set_point.rallies.won, set_point.rallies.all), (0, 1));
+        assert_eq!(set_point.phase.break_point.all, 1);
+        assert_eq!(
+            situation(&report.home, Situation::SetPoint)
+                .phase
+                .side_out
+                .won,
+            1
+        );
+        assert_eq!(
+            situation(&report.away, Situation::MatchPoint).rallies.all,
+            0
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Zone};
+use crate::stats::{Percentage, Skill};
+
+/// Where one kind of action went, each cell counts the actions into that zone
+/// and how often the acting team went on to win the rally.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Heatmap {
+    pub zones: [Percentage; 9],
+    pub sub_zones: [[Percentage; 4]; 9],
+}
+
+impl Heatmap {
+    fn new() -> Self {
+        Self {
+            zones: [Percentage::new(); 9],
+            sub_zones: [[Percentage::new(); 4]; 9],
+        }
+    }
+
+    fn record(self, zone: Zone, won: bool) -> Self {
+        let Some(number) = zone.number() else {
+            return self;
+        };
+        let idx = usize::from(number - 1);
+
+        let mut zones = self.zones;
+        zones[idx] = zones[idx].record(won);
+
+        let mut sub_zones = self.sub_zones;
+        if let Some(sub_zone) = zone.sub_zone() {
+            let cell = &mut sub_zones[idx][sub_zone.index()];
+            *cell = cell.record(won);
+        }
+
+        Self { zones, sub_zones }
+    }
+
+    /// One CSV row per zone and subzone that saw any action.
+    fn csv_rows(&self, prefix: &str) -> Vec<String> {
+        let row = |zone: usize, sub_zone: &str, cell: &Percentage| {
+            format!(
+                "{prefix},{},{sub_zone},{},{},{}",
+                zone + 1,
+                cell.all,
+                cell.won,
+                cell.percentage.map_or(String::new(), |p| p.to_string())
+            )
+        };
+
+        (0..9)
+            .flat_map(|zone| {
+                let total = Some(&self.zones[zone])
+                    .filter(|cell| cell.all > 0)
+                    .map(|cell| row(zone, "", cell));
+                let sub_zones = ["A", "B", "C", "D"]
+                    .iter()
+                    .zip(self.sub_zones[zone].iter())
+                    .filter(|(_, cell)| cell.all > 0)
+                    .map(move |(name, cell)| row(zone, name, cell));
+
+                total.into_iter().chain(sub_zones).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillHeatmaps {
+    pub serves: Heatmap,
+    pub attacks: Heatmap,
+    pub receptions: Heatmap,
+}
+
+impl SkillHeatmaps {
+    fn new() -> Self {
+        Self {
+            serves: Heatmap::new(),
+            attacks: Heatmap::new(),
+            receptions: Heatmap::new(),
+        }
+    }
+
+    fn record(self, action_type: ActionType, won: bool) -> Self {
+        match action_type {
+            ActionType::Serve(_, Some(zone)) => Self {
+                serves: self.serves.record(zone, won),
+                ..self
+            },
+            ActionType::Hit(_, Some(zone)) => Self {
+                attacks: self.attacks.record(zone, won),
+                ..self
+            },
+            ActionType::Receive(_, Some(zone)) => Self {
+                receptions: self.receptions.record(zone, won),
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    pub fn skill(&self, skill: Skill) -> Option<&Heatmap> {
+        match skill {
+            Skill::Serve => Some(&self.serves),
+            Skill::Hit => Some(&self.attacks),
+            Skill::Receive => Some(&self.receptions),
+            _ => None,
+        }
+    }
+
+    fn csv_rows(&self, prefix: &str) -> Vec<String> {
+        [
+            ("serve", &self.serves),
+            ("attack", &self.attacks),
+            ("reception", &self.receptions),
+        ]
+        .iter()
+        .flat_map(|(skill, heatmap)| heatmap.csv_rows(&format!("{prefix},{skill}")))
+        .collect()
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerHeatmaps {
+    pub player: Player,
+    #[serde(flatten)]
+    pub heatmaps: SkillHeatmaps,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamHeatmaps {
+    #[serde(flatten)]
+    pub total: SkillHeatmaps,
+    pub players: Vec<PlayerHeatmaps>,
+}
+
+impl TeamHeatmaps {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut total = SkillHeatmaps::new();
+        let mut players: BTreeMap<u8, SkillHeatmaps> = BTreeMap::new();
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            let won = rally.who.point_to == team;
+
+            rally
+                .actions
+                .iter()
+                .filter(|action| action.team == team)
+                .for_each(|action| {
+                    total = total.record(action.action_type, won);
+
+                    let player = players
+                        .entry(action.player.0)
+                        .or_insert(SkillHeatmaps::new());
+                    *player = player.record(action.action_type, won);
+                });
+        });
+
+        TeamHeatmaps {
+            total,
+            players: players
+                .into_iter()
+                .map(|(player, heatmaps)| PlayerHeatmaps {
+                    player: Player(player),
+                    heatmaps,
+                })
+                .collect(),
+        }
+    }
+
+    fn csv_rows(&self, team: &str) -> Vec<String> {
+        self.total
+            .csv_rows(&format!("{team},"))
+            .into_iter()
+            .chain(self.players.iter().flat_map(|player| {
+                player
+                    .heatmaps
+                    .csv_rows(&format!("{team},{}", player.player.0))
+            }))
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapReport {
+    pub away: TeamHeatmaps,
+    pub home: TeamHeatmaps,
+}
+
+impl HeatmapReport {
+    pub fn team(&self, team: Team) -> &TeamHeatmaps {
+        match team {
+            Team::Away => &self.away,
+            Team::Home => &self.home,
+        }
+    }
+
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            away: TeamHeatmaps::new(Team::Away, rallies),
+            home: TeamHeatmaps::new(Team::Home, rallies),
+        }
+    }
+
+    /// Every non-empty cell as CSV, the player is left empty for team totals
+    /// and the subzone for zone totals.
+    pub fn to_csv(&self) -> String {
+        let header = "team,player,skill,zone,subZone,count,won,percentage".to_string();
+
+        [header]
+            .into_iter()
+            .chain(self.away.csv_rows("away"))
+            .chain(self.home.csv_rows("home"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn csv_has_a_row_per_zone_and_subzone() {
+        let rallies = play(MatchHistory::new(MatchRules::default()), &["@1SA5B"]);
+        let csv = HeatmapReport::new(&rallies).to_csv();
+
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "team,player,skill,zone,subZone,count,won,percentage",
+                "away,,serve,5,,1,1,100",
+                "away,,serve,5,B,1,1,100",
+                "away,1,serve,5,,1,1,100",
+                "away,1,serve,5,B,1,1,100",
+            ]
+        );
+    }
+}
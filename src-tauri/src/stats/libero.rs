@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Height, Zone};
+
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactQuality {
+    pub attempts: u16,
+    pub errors: u16,
+    pub overpasses: u16,
+    pub low: u16,
+    pub mid: u16,
+    pub high: u16,
+}
+
+impl ContactQuality {
+    pub fn new() -> Self {
+        Self {
+            attempts: 0,
+            errors: 0,
+            overpasses: 0,
+            low: 0,
+            mid: 0,
+            high: 0,
+        }
+    }
+
+    pub fn record(self, height: Option<Height>, zone: Option<Zone>, error: bool) -> Self {
+        Self {
+            attempts: self.attempts + 1,
+            errors: self.errors + u16::from(error),
+            overpasses: self.overpasses + u16::from(matches!(zone, Some(Zone::Overpass))),
+            low: self.low + u16::from(matches!(height, Some(Height::Low))),
+            mid: self.mid + u16::from(matches!(height, Some(Height::Mid))),
+            high: self.high + u16::from(matches!(height, Some(Height::High))),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LiberoStats {
+    pub team: Team,
+    pub player: Player,
+    pub reception: ContactQuality,
+    /// Passes right after an attack of the opponent.
+    pub digs: ContactQuality,
+}
+
+impl LiberoStats {
+    fn new(team: Team, player: Player) -> Self {
+        Self {
+            team,
+            player,
+            reception: ContactQuality::new(),
+            digs: ContactQuality::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiberoReport {
+    pub liberos: Vec<LiberoStats>,
+}
+
+impl LiberoReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut liberos: HashMap<(Team, Player), LiberoStats> = HashMap::new();
+
+        rallies.iter().for_each(|PlayedRally { before, rally }| {
+            let faulted = rally.faulted_index();
+
+            rally
+                .actions
+                .iter()
+                .enumerate()
+                .filter(|(_, action)| before.is_libero(action.team, action.player))
+                .for_each(|(idx, action)| {
+                    let stats = liberos
+                        .entry((action.team, action.player))
+                        .or_insert(LiberoStats::new(action.team, action.player));
+                    let error = faulted == Some(idx);
+                    let after_attack = idx
+                        .checked_sub(1)
+                        .and_then(|prev| rally.actions.get(prev))
+                        .is_some_and(|prev| {
+                            prev.team != action.team
+                                && matches!(prev.action_type, ActionType::Hit(_, _))
+                        });
+
+                    match action.action_type {
+                        ActionType::Receive(height, zone) => {
+                            stats.reception = stats.reception.record(height, zone, error)
+                        }
+                        ActionType::Pass(height, zone) if after_attack => {
+                            stats.digs = stats.digs.record(height, zone, error)
+                        }
+                        _ => (),
+                    }
+                });
+        });
+
+        let mut liberos: Vec<LiberoStats> = liberos.into_values().collect();
+        liberos.sort_by_key(|stats| (stats.team, stats.player));
+
+        LiberoReport { liberos }
+    }
+}
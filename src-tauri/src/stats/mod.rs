@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::match_history::PlayedRally;
+#[cfg(test)]
+use crate::match_history::{MatchEvent, MatchHistory};
+use crate::parser::parser::{ActionType, Zone};
+
+pub mod attack;
+pub mod beach;
+pub mod clutch;
+pub mod heatmap;
+pub mod libero;
+pub mod momentum;
+pub mod phase;
+pub mod points;
+pub mod rally_length;
+pub mod reception;
+pub mod rotation;
+pub mod serving;
+pub mod setting;
+pub mod timeouts;
+
+/// Share of attempts that went well, e.g. rallies won out of all rallies received.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Percentage {
+    pub won: u16,
+    pub all: u16,
+    pub percentage: Option<f32>,
+}
+
+impl Percentage {
+    pub fn new() -> Self {
+        Self {
+            won: 0,
+            all: 0,
+            percentage: None,
+        }
+    }
+
+    fn from_counts(won: u16, all: u16) -> Self {
+        Self {
+            won,
+            all,
+            percentage: match all {
+                0 => None,
+                all => Some(f32::from(won) * 100.0 / f32::from(all)),
+            },
+        }
+    }
+
+    pub fn record(self, won: bool) -> Self {
+        Self::from_counts(self.won + u16::from(won), self.all + 1)
+    }
+}
+
+/// How often each court zone was hit, zones 1 through 9 and their subzones A
+/// through D. Subzones are only counted when they were entered.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneDistribution {
+    pub zones: [u16; 9],
+    pub sub_zones: [[u16; 4]; 9],
+}
+
+impl ZoneDistribution {
+    pub fn new() -> Self {
+        Self {
+            zones: [0; 9],
+            sub_zones: [[0; 4]; 9],
+        }
+    }
+
+    pub fn record(self, zone: Zone) -> Self {
+        let Some(number) = zone.number() else {
+            return self;
+        };
+        let idx = usize::from(number - 1);
+
+        let mut zones = self.zones;
+        zones[idx] += 1;
+
+        let mut sub_zones = self.sub_zones;
+        if let Some(sub_zone) = zone.sub_zone() {
+            sub_zones[idx][sub_zone.index()] += 1;
+        }
+
+        Self { zones, sub_zones }
+    }
+}
+
+/// The kind of an action without its details.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Skill {
+    Serve,
+    Receive,
+    Pass,
+    Set,
+    Hit,
+    Block,
+    Freeball,
+}
+
+impl From<ActionType> for Skill {
+    fn from(action_type: ActionType) -> Self {
+        match action_type {
+            ActionType::Serve(_, _) => Skill::Serve,
+            ActionType::Receive(_, _) => Skill::Receive,
+            ActionType::Pass(_, _) => Skill::Pass,
+            ActionType::Set => Skill::Set,
+            ActionType::Hit(_, _) => Skill::Hit,
+            ActionType::Block(_, _) => Skill::Block,
+            ActionType::Freeball(_) => Skill::Freeball,
+        }
+    }
+}
+
+/// Rallies grouped by the set they were played in, in set order.
+pub fn rallies_by_set(rallies: &[PlayedRally]) -> BTreeMap<u8, Vec<&PlayedRally>> {
+    let mut by_set: BTreeMap<u8, Vec<&PlayedRally>> = BTreeMap::new();
+    rallies.iter().for_each(|played| {
+        by_set
+            .entry(played.before.current_set())
+            .or_default()
+            .push(played)
+    });
+
+    by_set
+}
+
+/// Splits consecutive rallies into runs of points won by the same team.
+pub fn split_runs<'a, 'b>(rallies: &'b [&'a PlayedRally]) -> Vec<&'b [&'a PlayedRally]> {
+    rallies
+        .chunk_by(|a, b| a.rally.who.point_to == b.rally.who.point_to)
+        .collect()
+}
+
+/// The valid rallies after `rallies` were pushed to `history`, for testing the
+/// reports on notation.
+#[cfg(test)]
+pub fn play(history: MatchHistory, rallies: &[&str]) -> Vec<PlayedRally> {
+    let config = crate::Config::default();
+
+    rallies
+        .iter()
+        .try_fold(history, |history, rally| {
+            history.push(config, MatchEvent::Rally(rally.to_string()))
+        })
+        .unwrap()
+        .rallies(config)
+}
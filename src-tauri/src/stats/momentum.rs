@@ -0,0 +1,180 @@
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, ScoreMoment, Team};
+use crate::parser::parser::Rally;
+use crate::stats::{rallies_by_set, split_runs, Skill};
+
+/// Runs shorter than this aren't listed on their own.
+const MIN_RUN: u16 = 3;
+
+/// The rally that ended a run, won by the other team either through its own
+/// action or an error of the running team.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RunEnd {
+    pub team: Team,
+    pub player: Player,
+    pub skill: Skill,
+    pub error: bool,
+}
+
+impl RunEnd {
+    fn new(rally: &Rally) -> Option<Self> {
+        match (rally.who.scored, rally.who.faulted) {
+            (Some(scored), _) => Some(RunEnd {
+                team: scored.team,
+                player: scored.player,
+                skill: scored.action_type.into(),
+                error: false,
+            }),
+            (None, Some(faulted)) => Some(RunEnd {
+                team: faulted.team,
+                player: faulted.player,
+                skill: faulted.action_type.into(),
+                error: true,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Consecutive points of one team within a set, from the score before its
+/// first point to the score after its last one.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoringRun {
+    pub team: Team,
+    pub points: u16,
+    pub from: ScoreMoment,
+    pub to: ScoreMoment,
+    /// `None` if the set ended during the run or it's still going on.
+    pub ended_by: Option<RunEnd>,
+}
+
+/// The score right after `played`.
+fn score_after(played: &PlayedRally) -> ScoreMoment {
+    let before = played.before.score_moment();
+
+    match played.rally.who.point_to {
+        Team::Away => ScoreMoment {
+            away_points: before.away_points + 1,
+            ..before
+        },
+        Team::Home => ScoreMoment {
+            home_points: before.home_points + 1,
+            ..before
+        },
+    }
+}
+
+fn runs_of_set(rallies: &[&PlayedRally]) -> Vec<ScoringRun> {
+    let runs = split_runs(rallies);
+
+    runs.iter()
+        .enumerate()
+        .map(|(idx, run)| ScoringRun {
+            team: run[0].rally.who.point_to,
+            points: run.len() as u16,
+            from: run[0].before.score_moment(),
+            to: score_after(run[run.len() - 1]),
+            ended_by: runs
+                .get(idx + 1)
+                .and_then(|next| RunEnd::new(&next[0].rally)),
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetMomentum {
+    pub set: u8,
+    pub longest_away_run: Option<ScoringRun>,
+    pub longest_home_run: Option<ScoringRun>,
+    /// The score after every rally of the set, for a worm chart.
+    pub progression: Vec<ScoreMoment>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MomentumReport {
+    /// Every run of at least `MIN_RUN` points.
+    pub runs: Vec<ScoringRun>,
+    pub by_set: Vec<SetMomentum>,
+}
+
+impl MomentumReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut runs = Vec::new();
+        let by_set = rallies_by_set(rallies)
+            .into_iter()
+            .map(|(set, rallies)| {
+                let set_runs = runs_of_set(&rallies);
+                let longest = |team: Team| {
+                    set_runs
+                        .iter()
+                        .filter(|run| run.team == team)
+                        .max_by_key(|run| run.points)
+                        .copied()
+                };
+                let momentum = SetMomentum {
+                    set,
+                    longest_away_run: longest(Team::Away),
+                    longest_home_run: longest(Team::Home),
+                    progression: rallies.iter().map(|played| score_after(played)).collect(),
+                };
+
+                runs.extend(set_runs.into_iter().filter(|run| run.points >= MIN_RUN));
+                momentum
+            })
+            .collect();
+
+        MomentumReport { runs, by_set }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn runs_are_ended_by_the_next_rally() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &[
+                "@1S5",
+                "@1S5",
+                "@1S5",
+                "@1S5 !2R !3E !4H5",
+                "!1S5 @2R @3P",
+                "!1S0",
+            ],
+        );
+        let report = MomentumReport::new(&rallies);
+
+        assert_eq!(report.runs.len(), 1);
+        let run = report.runs[0];
+        assert!(run.team == Team::Away);
+        assert_eq!(run.points, 3);
+        assert_eq!((run.from.away_points, run.to.away_points), (0, 3));
+        let ended_by = run.ended_by.unwrap();
+        assert!(ended_by.team == Team::Home && ended_by.skill == Skill::Hit);
+        assert_eq!(ended_by.player.0, 4);
+        assert!(!ended_by.error);
+
+        let set = &report.by_set[0];
+        let home_run = set.longest_home_run.unwrap();
+        assert_eq!(home_run.points, 2);
+        // Lost to the running team's own serve error
+        let ended_by = home_run.ended_by.unwrap();
+        assert!(ended_by.team == Team::Home && ended_by.skill == Skill::Serve);
+        assert!(ended_by.error);
+        assert_eq!(set.longest_away_run.unwrap().points, 3);
+        assert_eq!(set.progression.len(), 6);
+        let last = set.progression[5];
+        assert_eq!((last.away_points, last.home_points), (4, 2));
+    }
+}
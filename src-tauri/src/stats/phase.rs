@@ -0,0 +1,186 @@
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{PlayerScores, Team};
+use crate::parser::parser::{ActionType, Rally};
+
+/// The phase of the rally an action was played in. A possession right off the
+/// serve reception is a side-out (K1), one after the opponent sent a freeball
+/// is a freeball phase and every other one is transition (K2).
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Serve,
+    SideOut,
+    Transition,
+    Freeball,
+}
+
+impl Phase {
+    /// The phase of every action of `rally`. Blocks belong to the phase of the
+    /// attack they're blocking, the possession only changes with the next
+    /// contact of the other team.
+    pub fn of_actions(rally: &Rally) -> Vec<Phase> {
+        let mut phases = Vec::with_capacity(rally.actions.len());
+        let mut possession = rally.serve().map(|serve| serve.team);
+        let mut phase = Phase::Serve;
+        let mut last_contact: Option<ActionType> = None;
+
+        rally.actions.iter().for_each(|action| {
+            if let ActionType::Block(_, _) = action.action_type {
+                phases.push(phase);
+                return;
+            }
+
+            if possession != Some(action.team) {
+                phase = match (phase, last_contact) {
+                    (Phase::Serve, _) => Phase::SideOut,
+                    (_, Some(ActionType::Freeball(_))) => Phase::Freeball,
+                    _ => Phase::Transition,
+                };
+                possession = Some(action.team);
+            }
+
+            phases.push(phase);
+            last_contact = Some(action.action_type);
+        });
+
+        phases
+    }
+}
+
+/// Blocks of a team split by the phase of the attack they blocked, attacks
+/// are split by phase in the `AttackReport`.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamPhaseStats {
+    pub side_out: PlayerScores,
+    pub transition: PlayerScores,
+    pub freeball: PlayerScores,
+}
+
+impl TeamPhaseStats {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut stats = Self {
+            side_out: PlayerScores::new(),
+            transition: PlayerScores::new(),
+            freeball: PlayerScores::new(),
+        };
+
+        let is_block = |action_type: ActionType| matches!(action_type, ActionType::Block(_, _));
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            let phases = Phase::of_actions(rally);
+            let last_two = rally.actions.len().saturating_sub(2);
+
+            rally
+                .actions
+                .iter()
+                .enumerate()
+                .filter(|(_, action)| action.team == team && is_block(action.action_type))
+                .for_each(|(idx, action)| {
+                    let decisive = idx >= last_two;
+                    let won = rally.who.point_to == team;
+                    let scored = decisive
+                        && won
+                        && rally
+                            .who
+                            .scored
+                            .is_some_and(|s| s.player == action.player && is_block(s.action_type));
+                    let faulted = decisive
+                        && !won
+                        && rally
+                            .who
+                            .faulted
+                            .is_some_and(|f| f.player == action.player && is_block(f.action_type));
+
+                    if let Some(blocks) = stats.blocks_mut(phases[idx]) {
+                        let attempt = blocks.attempt();
+                        *blocks = match (scored, faulted) {
+                            (true, _) => attempt.score(),
+                            (_, true) => attempt.fault(),
+                            _ => attempt,
+                        };
+                    }
+                });
+        });
+
+        stats
+    }
+
+    fn blocks_mut(&mut self, phase: Phase) -> Option<&mut PlayerScores> {
+        match phase {
+            Phase::Serve => None,
+            Phase::SideOut => Some(&mut self.side_out),
+            Phase::Transition => Some(&mut self.transition),
+            Phase::Freeball => Some(&mut self.freeball),
+        }
+    }
+}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseReport {
+    pub away: TeamPhaseStats,
+    pub home: TeamPhaseStats,
+}
+
+impl PhaseReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            away: TeamPhaseStats::new(Team::Away, rallies),
+            home: TeamPhaseStats::new(Team::Home, rallies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::parser::parser::parse;
+    use crate::stats::play;
+    use crate::Config;
+
+    #[test]
+    fn possessions_after_the_side_out_are_transition_or_freeball() {
+        let rally = parse(
+            Config::default(),
+            "@1S5 !2R !3E !4H5 @7B@ @5P @6E @7H5 !8P !9F @5P @6E @7H5",
+        )
+        .unwrap();
+        let (serve, side_out, transition, freeball) = (
+            Phase::Serve,
+            Phase::SideOut,
+            Phase::Transition,
+            Phase::Freeball,
+        );
+
+        assert!(
+            Phase::of_actions(&rally)
+                == vec![
+                    serve, side_out, side_out, side_out, side_out, transition, transition,
+                    transition, transition, transition, freeball, freeball, freeball,
+                ]
+        );
+    }
+
+    #[test]
+    fn blocks_are_split_by_the_phase_of_the_attack() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &[
+                "@1S5 !2R !3E !4H5 @7B!",
+                // Blocked onto the blocker's own side, the attacker scores
+                "@1S5 !2R !3E !4H5 @5P @6E @7H5 !8B!",
+            ],
+        );
+        let report = PhaseReport::new(&rallies);
+
+        assert_eq!(report.away.side_out.all, 1);
+        assert_eq!(report.away.side_out.scored, 1);
+        assert_eq!(report.away.transition.all, 0);
+        assert_eq!(report.home.transition.all, 1);
+        assert_eq!(report.home.transition.faults, 1);
+        assert_eq!(report.home.side_out.all, 0);
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::Team;
+use crate::parser::parser::{ActionType, WhoScored};
+
+/// How a team won its points. A point with both a scoring and a faulting player,
+/// like a block off an attack, counts as earned by the scoring player.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PointsBreakdown {
+    pub total: u16,
+    pub aces: u16,
+    pub kills: u16,
+    pub blocks: u16,
+    /// Points scored by any other contact, e.g. an attacking overpass or freeball.
+    pub other_earned: u16,
+    pub opponent_serve_errors: u16,
+    pub opponent_attack_errors: u16,
+    pub opponent_block_errors: u16,
+    /// Receptions, passes, sets and freeballs the opponent couldn't keep in play.
+    pub opponent_ball_handling_errors: u16,
+}
+
+impl PointsBreakdown {
+    fn new() -> Self {
+        Self {
+            total: 0,
+            aces: 0,
+            kills: 0,
+            blocks: 0,
+            other_earned: 0,
+            opponent_serve_errors: 0,
+            opponent_attack_errors: 0,
+            opponent_block_errors: 0,
+            opponent_ball_handling_errors: 0,
+        }
+    }
+
+    fn record(self, who: &WhoScored) -> Self {
+        let total = self.total + 1;
+
+        match (who.scored, who.faulted) {
+            (Some(scored), _) => match scored.action_type {
+                ActionType::Serve(_, _) => Self {
+                    total,
+                    aces: self.aces + 1,
+                    ..self
+                },
+                ActionType::Hit(_, _) => Self {
+                    total,
+                    kills: self.kills + 1,
+                    ..self
+                },
+                ActionType::Block(_, _) => Self {
+                    total,
+                    blocks: self.blocks + 1,
+                    ..self
+                },
+                _ => Self {
+                    total,
+                    other_earned: self.other_earned + 1,
+                    ..self
+                },
+            },
+            (None, Some(faulted)) => match faulted.action_type {
+                ActionType::Serve(_, _) => Self {
+                    total,
+                    opponent_serve_errors: self.opponent_serve_errors + 1,
+                    ..self
+                },
+                ActionType::Hit(_, _) => Self {
+                    total,
+                    opponent_attack_errors: self.opponent_attack_errors + 1,
+                    ..self
+                },
+                ActionType::Block(_, _) => Self {
+                    total,
+                    opponent_block_errors: self.opponent_block_errors + 1,
+                    ..self
+                },
+                _ => Self {
+                    total,
+                    opponent_ball_handling_errors: self.opponent_ball_handling_errors + 1,
+                    ..self
+                },
+            },
+            (None, None) => Self { total, ..self },
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPointsBreakdown {
+    pub set: u8,
+    pub points: PointsBreakdown,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamPointsBreakdown {
+    pub total: PointsBreakdown,
+    pub by_set: Vec<SetPointsBreakdown>,
+}
+
+impl TeamPointsBreakdown {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut total = PointsBreakdown::new();
+        let mut by_set: BTreeMap<u8, PointsBreakdown> = BTreeMap::new();
+
+        rallies
+            .iter()
+            .filter(|PlayedRally { rally, .. }| rally.who.point_to == team)
+            .for_each(|PlayedRally { before, rally }| {
+                total = total.record(&rally.who);
+
+                let set_points = by_set
+                    .entry(before.current_set())
+                    .or_insert(PointsBreakdown::new());
+                *set_points = set_points.record(&rally.who);
+            });
+
+        TeamPointsBreakdown {
+            total,
+            by_set: by_set
+                .into_iter()
+                .map(|(set, points)| SetPointsBreakdown { set, points })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointsReport {
+    pub away: TeamPointsBreakdown,
+    pub home: TeamPointsBreakdown,
+}
+
+impl PointsReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            away: TeamPointsBreakdown::new(Team::Away, rallies),
+            home: TeamPointsBreakdown::new(Team::Home, rallies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn points_are_split_into_earned_ones_and_opponent_errors() {
+        let rules = MatchRules {
+            points_per_set: 3,
+            min_margin: 1,
+            ..MatchRules::default()
+        };
+        let rallies = play(
+            MatchHistory::new(rules),
+            &[
+                "@1S5",
+                "@1S0",
+                "!1S5 @2R @3E @4H5",
+                "@1S5 !2R !3E !4H5 @7B!",
+                "!1S5 @2R @3P",
+                "!1S5 @2R @3E @4H0",
+            ],
+        );
+        let report = PointsReport::new(&rallies);
+        let (away, home) = (report.away.total, report.home.total);
+
+        assert_eq!(
+            (away.total, away.aces, away.kills, away.blocks),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(away.opponent_serve_errors, 0);
+        assert_eq!(home.total, 3);
+        assert_eq!(home.opponent_serve_errors, 1);
+        assert_eq!(home.opponent_ball_handling_errors, 1);
+        assert_eq!(home.opponent_attack_errors, 1);
+
+        let sets: Vec<(u8, u16)> = report
+            .home
+            .by_set
+            .iter()
+            .map(|set| (set.set, set.points.total))
+            .collect();
+        assert_eq!(sets, vec![(1, 1), (2, 2)]);
+        assert_eq!(report.away.by_set.len(), 1);
+    }
+}
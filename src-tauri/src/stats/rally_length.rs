@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::Team;
+use crate::parser::parser::Rally;
+use crate::stats::{rallies_by_set, Percentage};
+
+/// Times the ball changed sides, counted between consecutive contacts of
+/// different teams plus once more if it landed on the side of the team that
+/// didn't touch it last, e.g. an ace crossed the net once.
+fn net_crossings(rally: &Rally) -> u16 {
+    let changes = rally
+        .actions
+        .windows(2)
+        .filter(|pair| pair[0].team != pair[1].team)
+        .count();
+    let landed_across = rally
+        .actions
+        .last()
+        .is_some_and(|last| last.team == rally.who.point_to);
+
+    (changes + usize::from(landed_across)) as u16
+}
+
+/// Rallies of one length and how often each team won them.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LengthStats {
+    pub length: u16,
+    pub away: Percentage,
+    pub home: Percentage,
+}
+
+impl LengthStats {
+    fn new(length: u16) -> Self {
+        Self {
+            length,
+            away: Percentage::new(),
+            home: Percentage::new(),
+        }
+    }
+
+    fn record(self, point_to: Team) -> Self {
+        Self {
+            away: self.away.record(point_to == Team::Away),
+            home: self.home.record(point_to == Team::Home),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct AverageLength {
+    pub rallies: u16,
+    pub actions: Option<f32>,
+    pub crossings: Option<f32>,
+}
+
+impl AverageLength {
+    fn new(rallies: &[&PlayedRally]) -> Self {
+        let average = |length: &dyn Fn(&Rally) -> u16| match rallies.len() {
+            0 => None,
+            count => Some(
+                rallies
+                    .iter()
+                    .map(|played| f32::from(length(&played.rally)))
+                    .sum::<f32>()
+                    / count as f32,
+            ),
+        };
+
+        Self {
+            rallies: rallies.len() as u16,
+            actions: average(&|rally| rally.actions.len() as u16),
+            crossings: average(&net_crossings),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAverageLength {
+    pub set: u8,
+    pub average: AverageLength,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RallyLengthReport {
+    pub by_actions: Vec<LengthStats>,
+    pub by_crossings: Vec<LengthStats>,
+    pub average: AverageLength,
+    pub by_set: Vec<SetAverageLength>,
+}
+
+fn length_table(rallies: &[PlayedRally], length: impl Fn(&Rally) -> u16) -> Vec<LengthStats> {
+    let mut table: BTreeMap<u16, LengthStats> = BTreeMap::new();
+
+    rallies.iter().for_each(|PlayedRally { rally, .. }| {
+        let length = length(rally);
+        let stats = table.entry(length).or_insert(LengthStats::new(length));
+        *stats = stats.record(rally.who.point_to);
+    });
+
+    table.into_values().collect()
+}
+
+impl RallyLengthReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            by_actions: length_table(rallies, |rally| rally.actions.len() as u16),
+            by_crossings: length_table(rallies, net_crossings),
+            average: AverageLength::new(&rallies.iter().collect::<Vec<_>>()),
+            by_set: rallies_by_set(rallies)
+                .into_iter()
+                .map(|(set, rallies)| SetAverageLength {
+                    set,
+                    average: AverageLength::new(&rallies),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn rallies_are_measured_in_contacts_and_net_crossings() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &["@1S5", "@1S0", "!1S5 @2R @3E @4H5", "@1S5 !2R !3E !4H0"],
+        );
+        let crossings: Vec<u16> = rallies
+            .iter()
+            .map(|played| net_crossings(&played.rally))
+            .collect();
+
+        assert_eq!(crossings, vec![1, 0, 2, 1]);
+
+        let report = RallyLengthReport::new(&rallies);
+        let by_actions: Vec<(u16, u16, u16)> = report
+            .by_actions
+            .iter()
+            .map(|stats| (stats.length, stats.away.won, stats.away.all))
+            .collect();
+
+        assert_eq!(by_actions, vec![(1, 1, 2), (4, 2, 2)]);
+        assert_eq!(report.by_crossings.len(), 3);
+        assert_eq!(report.average.actions, Some(2.5));
+        assert_eq!(report.average.crossings, Some(1.0));
+        assert_eq!(report.by_set[0].average.rallies, 4);
+    }
+}
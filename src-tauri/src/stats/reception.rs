@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Height, Rally, Zone};
+use crate::stats::libero::ContactQuality;
+use crate::stats::Percentage;
+
+/// A perfect reception reaches the setter's zones 2 or 3 without being low, a
+/// positive one is perfect or still lets the team attack. Efficiency is
+/// (positive - errors - overpasses) / attempts.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceptionStats {
+    #[serde(flatten)]
+    pub contacts: ContactQuality,
+    /// Receptions by the zone they were passed to, zones 1 through 9.
+    pub by_zone: [u16; 9],
+    pub perfect: Percentage,
+    pub positive: Percentage,
+    pub efficiency: Option<f32>,
+}
+
+impl ReceptionStats {
+    pub fn new() -> Self {
+        Self {
+            contacts: ContactQuality::new(),
+            by_zone: [0; 9],
+            perfect: Percentage::new(),
+            positive: Percentage::new(),
+            efficiency: None,
+        }
+    }
+
+    pub fn record(self, reception: &Reception) -> Self {
+        let contacts = self
+            .contacts
+            .record(reception.height, reception.zone, reception.error);
+        let positive = self.positive.record(reception.positive);
+
+        let mut by_zone = self.by_zone;
+        if let Some(number) = reception.zone.and_then(|zone| zone.number()) {
+            by_zone[usize::from(number - 1)] += 1;
+        }
+
+        Self {
+            contacts,
+            by_zone,
+            perfect: self.perfect.record(reception.perfect),
+            positive,
+            efficiency: Some(
+                (f32::from(positive.won)
+                    - f32::from(contacts.errors)
+                    - f32::from(contacts.overpasses))
+                    / f32::from(contacts.attempts),
+            ),
+        }
+    }
+}
+
+/// A serve reception graded from the rest of the rally.
+pub struct Reception {
+    pub team: Team,
+    /// `None` for an ace nobody touched, it's only an error of the team.
+    pub player: Option<Player>,
+    pub height: Option<Height>,
+    pub zone: Option<Zone>,
+    pub error: bool,
+    pub perfect: bool,
+    pub positive: bool,
+}
+
+impl Reception {
+    fn untouched_ace(team: Team) -> Self {
+        Reception {
+            team,
+            player: None,
+            height: None,
+            zone: None,
+            error: true,
+            perfect: false,
+            positive: false,
+        }
+    }
+
+    pub fn all(rally: &Rally) -> Vec<Reception> {
+        let faulted = rally.faulted_index();
+
+        let receptions: Vec<Reception> = rally
+            .actions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, action)| match action.action_type {
+                ActionType::Receive(height, zone) => {
+                    let perfect = matches!(zone, Some(Zone::Two(_) | Zone::Three(_)))
+                        && !matches!(height, Some(Height::Low));
+                    let attacked = rally.actions[idx + 1..]
+                        .iter()
+                        .take_while(|next| next.team == action.team)
+                        .any(|next| matches!(next.action_type, ActionType::Hit(_, _)));
+                    let error =
+                        faulted == Some(idx) || matches!(zone, Some(Zone::OutOfBounds | Zone::Net));
+
+                    Some(Reception {
+                        team: action.team,
+                        player: Some(action.player),
+                        height,
+                        zone,
+                        error,
+                        perfect,
+                        positive: !error && (perfect || attacked),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        match rally.who.scored {
+            Some(scored)
+                if receptions.is_empty()
+                    && matches!(scored.action_type, ActionType::Serve(_, _)) =>
+            {
+                vec![Reception::untouched_ace(scored.team.get_opponent())]
+            }
+            _ => receptions,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerReceptionStats {
+    pub team: Team,
+    pub player: Player,
+    pub stats: ReceptionStats,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationReceptionStats {
+    pub rotation: u8,
+    pub stats: ReceptionStats,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerReceptionStats {
+    /// The opposing player who served.
+    pub server: Player,
+    pub stats: ReceptionStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamReceptionStats {
+    /// Also counts the aces nobody touched, as errors.
+    pub total: ReceptionStats,
+    /// Rallies played before the team's lineup was entered are left out.
+    pub by_rotation: Vec<RotationReceptionStats>,
+    pub by_server: Vec<ServerReceptionStats>,
+}
+
+impl TeamReceptionStats {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut total = ReceptionStats::new();
+        let mut by_rotation: BTreeMap<u8, ReceptionStats> = BTreeMap::new();
+        let mut by_server: BTreeMap<u8, ReceptionStats> = BTreeMap::new();
+
+        rallies.iter().for_each(|PlayedRally { before, rally }| {
+            let server = rally.serve().map(|serve| serve.player);
+
+            Reception::all(rally)
+                .iter()
+                .filter(|reception| reception.team == team)
+                .for_each(|reception| {
+                    total = total.record(reception);
+
+                    if let Some(rotation) = before.rotation(team) {
+                        let stats = by_rotation.entry(rotation).or_default();
+                        *stats = stats.record(reception);
+                    }
+
+                    if let Some(server) = server {
+                        let stats = by_server.entry(server.0).or_default();
+                        *stats = stats.record(reception);
+                    }
+                });
+        });
+
+        TeamReceptionStats {
+            total,
+            by_rotation: by_rotation
+                .into_iter()
+                .map(|(rotation, stats)| RotationReceptionStats { rotation, stats })
+                .collect(),
+            by_server: by_server
+                .into_iter()
+                .map(|(server, stats)| ServerReceptionStats {
+                    server: Player(server),
+                    stats,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceptionReport {
+    pub players: Vec<PlayerReceptionStats>,
+    pub away: TeamReceptionStats,
+    pub home: TeamReceptionStats,
+}
+
+impl ReceptionReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut players: HashMap<(Team, Player), ReceptionStats> = HashMap::new();
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            Reception::all(rally).iter().for_each(|reception| {
+                if let Some(player) = reception.player {
+                    let stats = players.entry((reception.team, player)).or_default();
+                    *stats = stats.record(reception);
+                }
+            });
+        });
+
+        let mut players: Vec<PlayerReceptionStats> = players
+            .into_iter()
+            .map(|((team, player), stats)| PlayerReceptionStats {
+                team,
+                player,
+                stats,
+            })
+            .collect();
+        players.sort_by_key(|stats| (stats.team, stats.player));
+
+        ReceptionReport {
+            players,
+            away: TeamReceptionStats::new(Team::Away, rallies),
+            home: TeamReceptionStats::new(Team::Home, rallies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lineup::Lineup;
+    use crate::match_history::{MatchEvent, MatchHistory};
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+    use crate::Config;
+
+    #[test]
+    fn aces_count_against_the_receiving_team() {
+        let lineup = MatchEvent::Lineup {
+            team: Team::Home,
+            lineup: Lineup::new([1, 2, 3, 4, 5, 6].map(Player)).unwrap(),
+        };
+        let history = MatchHistory::new(MatchRules::default())
+            .push(Config::default(), lineup)
+            .unwrap();
+        // An ace nobody touched, one off the receiver and a perfect reception
+        let rallies = play(history, &["@10S5", "@10S5 !2R", "@10S5 !2R2 !3E !4H5"]);
+        let report = ReceptionReport::new(&rallies);
+        let total = report.home.total;
+
+        assert_eq!(total.contacts.attempts, 3);
+        assert_eq!(total.contacts.errors, 2);
+        assert_eq!(total.perfect.won, 1);
+        assert_eq!(report.home.by_rotation[0].rotation, 1);
+        assert_eq!(report.home.by_rotation[0].stats.contacts.errors, 2);
+        assert_eq!(report.home.by_server[0].server.0, 10);
+        assert_eq!(report.home.by_server[0].stats.contacts.attempts, 3);
+        assert_eq!(report.away.total.contacts.attempts, 0);
+
+        // Only the touched ace is the receiver's own error
+        assert_eq!(report.players.len(), 1);
+        assert_eq!(report.players[0].player.0, 2);
+        assert_eq!(report.players[0].stats.contacts.attempts, 2);
+        assert_eq!(report.players[0].stats.contacts.errors, 1);
+    }
+}
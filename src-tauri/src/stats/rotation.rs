@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::Team;
+use crate::stats::attack::AttackStats;
+use crate::stats::rallies_by_set;
+use crate::stats::serving::PhaseStats;
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationStats {
+    pub rotation: u8,
+    pub points_won: u16,
+    pub points_lost: u16,
+    #[serde(flatten)]
+    pub phase: PhaseStats,
+    pub attack: AttackStats,
+}
+
+impl RotationStats {
+    fn new(rotation: u8) -> Self {
+        Self {
+            rotation,
+            points_won: 0,
+            points_lost: 0,
+            phase: PhaseStats::new(),
+            attack: AttackStats::new(),
+        }
+    }
+
+    fn record(self, team: Team, PlayedRally { rally, .. }: &PlayedRally) -> Self {
+        let Some(serve) = rally.serve() else {
+            return self;
+        };
+        let won = rally.who.point_to == team;
+
+        Self {
+            points_won: self.points_won + u16::from(won),
+            points_lost: self.points_lost + u16::from(!won),
+            phase: self.phase.record(team, serve.team, rally.who.point_to),
+            attack: self.attack.record(team, rally),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetRotationStats {
+    pub set: u8,
+    pub rotations: Vec<RotationStats>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamRotationStats {
+    pub total: Vec<RotationStats>,
+    pub by_set: Vec<SetRotationStats>,
+}
+
+/// All six rotations, including the ones that didn't play a rally yet.
+fn rotation_table(rallies: &[&PlayedRally], team: Team) -> Vec<RotationStats> {
+    (1..=6)
+        .map(|rotation| {
+            rallies
+                .iter()
+                .filter(|played| played.before.rotation(team) == Some(rotation))
+                .fold(RotationStats::new(rotation), |stats, played| {
+                    stats.record(team, played)
+                })
+        })
+        .collect()
+}
+
+impl TeamRotationStats {
+    /// Rallies played before the team's lineup was entered are left out.
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        TeamRotationStats {
+            total: rotation_table(&rallies.iter().collect::<Vec<_>>(), team),
+            by_set: rallies_by_set(rallies)
+                .into_iter()
+                .map(|(set, rallies)| SetRotationStats {
+                    set,
+                    rotations: rotation_table(&rallies, team),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationReport {
+    pub away: TeamRotationStats,
+    pub home: TeamRotationStats,
+}
+
+impl RotationReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            away: TeamRotationStats::new(Team::Away, rallies),
+            home: TeamRotationStats::new(Team::Home, rallies),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Rally, ServePosition, Zone};
+use crate::stats::{Percentage, ZoneDistribution};
+
+/// Side-out % counts the rallies a team won while receiving, break-point %
+/// the rallies it won while serving.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseStats {
+    pub side_out: Percentage,
+    pub break_point: Percentage,
+}
+
+impl PhaseStats {
+    pub fn new() -> Self {
+        Self {
+            side_out: Percentage::new(),
+            break_point: Percentage::new(),
+        }
+    }
+
+    pub fn record(self, team: Team, serving_team: Team, point_to: Team) -> Self {
+        let won = point_to == team;
+
+        match serving_team == team {
+            true => Self {
+                break_point: self.break_point.record(won),
+                ..self
+            },
+            false => Self {
+                side_out: self.side_out.record(won),
+                ..self
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPhaseStats {
+    pub set: u8,
+    pub stats: PhaseStats,
+}
+
+/// What the serves themselves did, regardless of who went on to win the rally.
+#[derive(Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServeStats {
+    pub attempts: u16,
+    pub aces: u16,
+    pub net_errors: u16,
+    pub out_errors: u16,
+    /// The reception went straight back over the net.
+    pub overpasses: u16,
+    /// The receiving team could only send a freeball back.
+    pub freeballs: u16,
+    pub target: ZoneDistribution,
+}
+
+impl ServeStats {
+    pub fn new() -> Self {
+        Self {
+            attempts: 0,
+            aces: 0,
+            net_errors: 0,
+            out_errors: 0,
+            overpasses: 0,
+            freeballs: 0,
+            target: ZoneDistribution::new(),
+        }
+    }
+
+    pub fn record(self, rally: &Rally) -> Self {
+        let Some(serve) = rally.serve() else {
+            return self;
+        };
+        let ActionType::Serve(_, zone) = serve.action_type else {
+            return self;
+        };
+
+        let is_serve = |action_type: ActionType| matches!(action_type, ActionType::Serve(_, _));
+        let ace = rally.who.point_to == serve.team
+            && rally.who.scored.is_some_and(|s| is_serve(s.action_type));
+        let error = rally.who.point_to != serve.team
+            && rally.who.faulted.is_some_and(|f| is_serve(f.action_type));
+        let mut first_ball = rally
+            .actions
+            .iter()
+            .skip(1)
+            .take_while(|action| action.team != serve.team);
+        let overpass = first_ball.clone().any(|action| {
+            matches!(
+                action.action_type,
+                ActionType::Receive(_, Some(Zone::Overpass))
+                    | ActionType::Pass(_, Some(Zone::Overpass))
+            )
+        });
+        let freeball =
+            first_ball.any(|action| matches!(action.action_type, ActionType::Freeball(_)));
+
+        Self {
+            attempts: self.attempts + 1,
+            aces: self.aces + u16::from(ace),
+            net_errors: self.net_errors + u16::from(error && matches!(zone, Some(Zone::Net))),
+            out_errors: self.out_errors
+                + u16::from(error && matches!(zone, Some(Zone::OutOfBounds))),
+            overpasses: self.overpasses + u16::from(overpass),
+            freeballs: self.freeballs + u16::from(freeball),
+            target: zone.map_or(self.target, |zone| self.target.record(zone)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStats {
+    pub player: Player,
+    pub break_point: Percentage,
+    pub serves: ServeStats,
+}
+
+impl ServerStats {
+    fn new(player: Player) -> Self {
+        Self {
+            player,
+            break_point: Percentage::new(),
+            serves: ServeStats::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionServeStats {
+    pub position: ServePosition,
+    pub serves: ServeStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamServingStats {
+    pub total: PhaseStats,
+    pub by_set: Vec<SetPhaseStats>,
+    pub by_server: Vec<ServerStats>,
+    pub serves: ServeStats,
+    /// Only serves with their serve position entered.
+    pub by_position: Vec<PositionServeStats>,
+}
+
+impl TeamServingStats {
+    fn new(team: Team, rallies: &[PlayedRally]) -> Self {
+        let mut total = PhaseStats::new();
+        let mut by_set: BTreeMap<u8, PhaseStats> = BTreeMap::new();
+        let mut by_server: HashMap<Player, ServerStats> = HashMap::new();
+        let mut serves = ServeStats::new();
+        let mut by_position: BTreeMap<ServePosition, ServeStats> = BTreeMap::new();
+
+        rallies.iter().for_each(|PlayedRally { before, rally }| {
+            let Some(serve) = rally.serve() else {
+                return;
+            };
+            let point_to = rally.who.point_to;
+
+            total = total.record(team, serve.team, point_to);
+
+            let set_stats = by_set.entry(before.current_set()).or_default();
+            *set_stats = set_stats.record(team, serve.team, point_to);
+
+            if serve.team != team {
+                return;
+            }
+
+            let server_stats = by_server
+                .entry(serve.player)
+                .or_insert(ServerStats::new(serve.player));
+            *server_stats = ServerStats {
+                break_point: server_stats.break_point.record(point_to == team),
+                serves: server_stats.serves.record(rally),
+                ..*server_stats
+            };
+
+            serves = serves.record(rally);
+
+            if let ActionType::Serve(Some(position), _) = serve.action_type {
+                let position_stats = by_position.entry(position).or_default();
+                *position_stats = position_stats.record(rally);
+            }
+        });
+
+        let mut by_server: Vec<ServerStats> = by_server.into_values().collect();
+        by_server.sort_by_key(|stats| stats.player.0);
+
+        TeamServingStats {
+            total,
+            by_set: by_set
+                .into_iter()
+                .map(|(set, stats)| SetPhaseStats { set, stats })
+                .collect(),
+            by_server,
+            serves,
+            by_position: by_position
+                .into_iter()
+                .map(|(position, serves)| PositionServeStats { position, serves })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServingStats {
+    pub away: TeamServingStats,
+    pub home: TeamServingStats,
+}
+
+impl ServingStats {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        Self {
+            away: TeamServingStats::new(Team::Away, rallies),
+            home: TeamServingStats::new(Team::Home, rallies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn serves_are_graded_by_their_outcome() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &["@1SA5B", "@1S5", "@1SN", "!2S0", "@1S6 !3RV"],
+        );
+        let report = ServingStats::new(&rallies);
+        let serves = report.away.serves;
+
+        assert_eq!(serves.attempts, 4);
+        assert_eq!(serves.aces, 2);
+        assert_eq!(serves.net_errors, 1);
+        assert_eq!(serves.out_errors, 0);
+        assert_eq!(serves.overpasses, 1);
+        assert_eq!(serves.target.zones[4], 2);
+        assert_eq!(serves.target.zones[5], 1);
+        assert_eq!(serves.target.sub_zones[4][1], 1);
+        assert_eq!(report.home.serves.out_errors, 1);
+
+        assert_eq!(report.away.by_position.len(), 1);
+        assert!(report.away.by_position[0].position == ServePosition::A);
+        assert_eq!(report.away.by_position[0].serves.aces, 1);
+
+        let server = report.away.by_server[0];
+        assert_eq!(server.player.0, 1);
+        assert_eq!((server.break_point.won, server.break_point.all), (2, 4));
+        assert_eq!(report.away.total.side_out.won, 1);
+    }
+}
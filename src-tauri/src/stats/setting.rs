@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{Player, Team};
+use crate::parser::parser::{ActionType, Rally};
+use crate::stats::attack::{Attack, AttackStats};
+
+/// A `Set` and the attack it was set for, if the next contact was a `Hit` of
+/// the same team.
+pub struct SetAction {
+    pub team: Team,
+    pub setter: Player,
+    pub attack: Option<Attack>,
+}
+
+impl SetAction {
+    pub fn all(rally: &Rally) -> Vec<SetAction> {
+        let attacks = Attack::all(rally);
+
+        rally
+            .actions
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| matches!(action.action_type, ActionType::Set))
+            .map(|(idx, action)| SetAction {
+                team: action.team,
+                setter: action.player,
+                attack: attacks
+                    .iter()
+                    .find(|attack| attack.index == idx + 1 && attack.team == action.team)
+                    .copied(),
+            })
+            .collect()
+    }
+}
+
+/// An assist is a set the attacker scored off, kill % is assists out of sets.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingStats {
+    pub sets: u16,
+    pub assists: u16,
+    pub kill_percentage: Option<f32>,
+}
+
+impl SettingStats {
+    pub fn new() -> Self {
+        Self {
+            sets: 0,
+            assists: 0,
+            kill_percentage: None,
+        }
+    }
+
+    fn from_counts(sets: u16, assists: u16) -> Self {
+        Self {
+            sets,
+            assists,
+            kill_percentage: match sets {
+                0 => None,
+                sets => Some(f32::from(assists) * 100.0 / f32::from(sets)),
+            },
+        }
+    }
+
+    pub fn record(self, set: &SetAction) -> Self {
+        Self::from_counts(
+            self.sets + 1,
+            self.assists + u16::from(set.attack.is_some_and(|attack| attack.kill)),
+        )
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self::from_counts(self.sets + other.sets, self.assists + other.assists)
+    }
+}
+
+/// How an attacker did on the balls one setter gave them.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct FedAttacker {
+    pub attacker: Player,
+    pub attack: AttackStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetterStats {
+    pub team: Team,
+    pub player: Player,
+    pub setting: SettingStats,
+    pub distribution: Vec<FedAttacker>,
+}
+
+impl SetterStats {
+    fn new(team: Team, player: Player) -> Self {
+        Self {
+            team,
+            player,
+            setting: SettingStats::new(),
+            distribution: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingReport {
+    pub setters: Vec<SetterStats>,
+}
+
+impl SettingReport {
+    pub fn new(rallies: &[PlayedRally]) -> Self {
+        let mut setting: HashMap<(Team, Player), SettingStats> = HashMap::new();
+        let mut fed: HashMap<(Team, Player), BTreeMap<u8, AttackStats>> = HashMap::new();
+
+        rallies.iter().for_each(|PlayedRally { rally, .. }| {
+            SetAction::all(rally).iter().for_each(|set| {
+                let stats = setting.entry((set.team, set.setter)).or_default();
+                *stats = stats.record(set);
+
+                if let Some(attack) = set.attack {
+                    let attack_stats = fed
+                        .entry((set.team, set.setter))
+                        .or_default()
+                        .entry(attack.player.0)
+                        .or_default();
+                    *attack_stats = attack_stats.record_attack(&attack);
+                }
+            });
+        });
+
+        let mut setters: Vec<SetterStats> = setting
+            .into_iter()
+            .map(|((team, player), setting)| SetterStats {
+                setting,
+                distribution: fed
+                    .remove(&(team, player))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(attacker, attack)| FedAttacker {
+                        attacker: Player(attacker),
+                        attack,
+                    })
+                    .collect(),
+                ..SetterStats::new(team, player)
+            })
+            .collect();
+        setters.sort_by_key(|stats| (stats.team, stats.player));
+
+        SettingReport { setters }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::MatchHistory;
+    use crate::match_rules::MatchRules;
+    use crate::stats::play;
+
+    #[test]
+    fn setters_are_credited_with_the_kills_they_set_up() {
+        let rallies = play(
+            MatchHistory::new(MatchRules::default()),
+            &[
+                "@1S5 !2R !3E !4H5",
+                "!1S5 @2R @3E @4H0",
+                "!1S5 @2R @3E @5H5 !6P !3E !4H0",
+                // A set nobody attacked
+                "@1S5 !2R !3E !2P",
+            ],
+        );
+        let report = SettingReport::new(&rallies);
+        let [away, home] = &report.setters[..] else {
+            panic!("expected one setter per team");
+        };
+
+        assert!(away.team == Team::Away && home.team == Team::Home);
+        assert_eq!(home.setting.sets, 3);
+        assert_eq!(home.setting.assists, 1);
+        assert_eq!(home.distribution.len(), 1);
+        assert_eq!(home.distribution[0].attacker.0, 4);
+        assert_eq!(home.distribution[0].attack.attempts, 2);
+        assert_eq!(home.distribution[0].attack.kills, 1);
+
+        assert_eq!(away.setting.sets, 2);
+        assert_eq!(away.setting.assists, 0);
+        assert_eq!(away.setting.kill_percentage, Some(0.0));
+        let attackers: Vec<u8> = away.distribution.iter().map(|fed| fed.attacker.0).collect();
+        assert_eq!(attackers, vec![4, 5]);
+    }
+}
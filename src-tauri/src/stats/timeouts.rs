@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+use crate::match_history::PlayedRally;
+use crate::match_state::{MatchState, ScoreMoment, Team};
+use crate::stats::split_runs;
+
+/// Consecutive points won by one team.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    pub team: Team,
+    pub points: u16,
+}
+
+impl Run {
+    fn new(rallies: &[&PlayedRally]) -> Self {
+        Run {
+            team: rallies[0].rally.who.point_to,
+            points: rallies.len() as u16,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutEffect {
+    /// `None` for technical timeouts.
+    pub team: Option<Team>,
+    pub moment: ScoreMoment,
+    /// The run that was going on when the timeout was taken.
+    pub run_before: Option<Run>,
+    /// The first run after the timeout.
+    pub run_after: Option<Run>,
+}
+
+impl TimeoutEffect {
+    fn new(team: Option<Team>, moment: ScoreMoment, rallies: &[PlayedRally]) -> Self {
+        let set_rallies: Vec<&PlayedRally> = rallies
+            .iter()
+            .filter(|played| played.before.current_set() == moment.set)
+            .collect();
+        let split = set_rallies
+            .iter()
+            .position(|played| played.before.score_moment() == moment)
+            .unwrap_or(set_rallies.len());
+        let (before, after) = set_rallies.split_at(split);
+
+        TimeoutEffect {
+            team,
+            moment,
+            run_before: split_runs(before).last().map(|run| Run::new(run)),
+            run_after: split_runs(after).first().map(|run| Run::new(run)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutReport {
+    pub timeouts: Vec<TimeoutEffect>,
+    pub technical_timeouts: Vec<TimeoutEffect>,
+}
+
+impl TimeoutReport {
+    pub fn new(state: &MatchState, rallies: &[PlayedRally]) -> Self {
+        TimeoutReport {
+            timeouts: state
+                .timeouts()
+                .iter()
+                .map(|timeout| TimeoutEffect::new(Some(timeout.team), timeout.moment, rallies))
+                .collect(),
+            technical_timeouts: state
+                .technical_timeouts()
+                .iter()
+                .map(|moment| TimeoutEffect::new(None, *moment, rallies))
+                .collect(),
+        }
+    }
+}
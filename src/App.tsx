@@ -1,9 +1,10 @@
-import { createSignal, Show } from "solid-js";
+import { createSignal, For, onCleanup, onMount, Show } from "solid-js";
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
 import { TextField, TextFieldRoot } from "@/components/ui/textfield";
 
-interface ParseResult {
-  Ok?: Stats;
+interface CommandResult<T> {
+  Ok?: T;
   Fail?: Reason;
 };
 
@@ -13,11 +14,32 @@ interface PlayerScores {
   all: number;
 }
 
+interface AttackStats {
+  attempts: number;
+  kills: number;
+  errors: number;
+  blocked: number;
+  efficiency: number | null;
+  killPercentage: number | null;
+}
+
+interface SettingStats {
+  sets: number;
+  assists: number;
+  killPercentage: number | null;
+}
+
 interface PlayerStats {
   player: number,
   hits: PlayerScores,
   blocks: PlayerScores,
   serves: PlayerScores,
+  receives: PlayerScores,
+  passes: PlayerScores,
+  sets: PlayerScores,
+  freeballs: PlayerScores,
+  attack: AttackStats,
+  setting: SettingStats,
 }
 
 interface StatsByPlayer {
@@ -27,13 +49,67 @@ interface StatsByPlayer {
 interface TeamStats {
   sets: number;
   points: number;
+  players: number[];
+  liberos: number[];
+  lineup: Lineup | null;
+  substitutions: Substitutions;
+  injured: number[];
   playerStats: StatsByPlayer
 }
 
+interface BeachRules {
+  sideSwitchEvery: number;
+  decidingSideSwitchEvery: number;
+  technicalTimeoutAt: number | null;
+}
+
+type Discipline = "Indoor" | { Beach: BeachRules };
+
+interface MatchRules {
+  discipline: Discipline;
+  setsToWin: number;
+  pointsPerSet: number;
+  decidingSetPoints: number;
+  minMargin: number;
+  pointCap: number | null;
+  liberoCanServe: boolean;
+  maxSubstitutions: number;
+  timeoutsPerSet: number;
+  technicalTimeouts: number[];
+}
+
+interface RulesPreset {
+  name: string;
+  rules: MatchRules;
+}
+
+interface ScoreMoment {
+  set: number;
+  awayPoints: number;
+  homePoints: number;
+}
+
+type Team = "Away" | "Home";
+
+interface SetScore {
+  set: number;
+  awayPoints: number;
+  homePoints: number;
+  winner: Team;
+  durationSecs: number | null;
+}
+
 interface Stats {
+  rules: MatchRules;
   awayTeam: TeamStats;
   homeTeam: TeamStats;
   status: "InProgress" | "Finished";
+  servingTeam: Team | null;
+  server: number | null;
+  sideSwitches: ScoreMoment[];
+  timeouts: { team: Team; moment: ScoreMoment }[];
+  technicalTimeouts: ScoreMoment[];
+  setScores: SetScore[];
 }
 
 interface Reason {
@@ -41,42 +117,267 @@ interface Reason {
   location: number;
 };
 
+interface LiberoSwap {
+  libero: number;
+  replaced: number;
+}
+
+interface SubstitutionPair {
+  starter: number;
+  substitute: number;
+  returned: boolean;
+}
+
+interface Substitutions {
+  pairs: SubstitutionPair[];
+  count: number;
+}
+
+interface Lineup {
+  positions: number[];
+  rotation: number;
+  libero: LiberoSwap | null;
+}
+
+type MatchEvent =
+  | { Rally: string }
+  | { Lineup: { team: Team; lineup: Lineup } }
+  | { Substitution: { team: Team; playerOut: number; playerIn: number; exceptional: boolean } }
+  | { Libero: { team: Team; libero: number } }
+  | { LiberoReplacement: { team: Team; libero: number; player: number } }
+  | { Timeout: { team: Team } };
+
+const describeEvent = (event: MatchEvent) => {
+  if ("Rally" in event) {
+    return event.Rally;
+  }
+  if ("Lineup" in event) {
+    return `${event.Lineup.team} lineup: ${event.Lineup.lineup.positions.join(" ")}`;
+  }
+  if ("Substitution" in event) {
+    const kind = event.Substitution.exceptional ? "exceptional substitution" : "substitution";
+    return `${event.Substitution.team} ${kind}: ${event.Substitution.playerIn} for ${event.Substitution.playerOut}`;
+  }
+  if ("Libero" in event) {
+    return `${event.Libero.team} libero: ${event.Libero.libero}`;
+  }
+  if ("LiberoReplacement" in event) {
+    return `${event.LiberoReplacement.team} libero ${event.LiberoReplacement.libero} swaps with ${event.LiberoReplacement.player}`;
+  }
+  return `${event.Timeout.team} timeout`;
+}
+
+// e.g. "25-21 (24 min)", the home team first like the scoreboard
+const describeSetScore = (score: SetScore) => {
+  const duration = score.durationSecs === null ? "" : ` (${Math.round(score.durationSecs / 60)} min)`;
+
+  return `${score.homePoints}-${score.awayPoints}${duration}`;
+}
+
+// Parses space separated player numbers, e.g. "1 7 12"
+const parsePlayers = (input: string) => input.trim().split(/\s+/).map(Number);
+
+// Unix time in seconds the event was recorded at
+type LoggedEvent = MatchEvent & { at: number | null };
+
+interface MatchHistory {
+  events: LoggedEvent[];
+  undone: LoggedEvent[];
+}
+
+interface InvalidEvent {
+  index: number;
+  reason: Reason;
+}
+
+interface MatchSnapshot {
+  history: MatchHistory;
+  state: Stats;
+  invalid: InvalidEvent[];
+}
+
 const initialStats: Stats = {
+  rules: {
+    discipline: "Indoor",
+    setsToWin: 3,
+    pointsPerSet: 25,
+    decidingSetPoints: 15,
+    minMargin: 2,
+    pointCap: null,
+    liberoCanServe: false,
+    maxSubstitutions: 6,
+    timeoutsPerSet: 2,
+    technicalTimeouts: [],
+  },
   awayTeam: {
     sets: 0,
     points: 0,
+    players: [],
+    liberos: [],
+    lineup: null,
+    substitutions: { pairs: [], count: 0 },
+    injured: [],
     playerStats: {},
   },
   homeTeam: {
     sets: 0,
     points: 0,
+    players: [],
+    liberos: [],
+    lineup: null,
+    substitutions: { pairs: [], count: 0 },
+    injured: [],
     playerStats: {},
   },
   status: "InProgress",
+  servingTeam: null,
+  server: null,
+  sideSwitches: [],
+  timeouts: [],
+  technicalTimeouts: [],
+  setScores: [],
+}
+
+const initialHistory: MatchHistory = {
+  events: [],
+  undone: [],
 }
 
 function App() {
   const [rally, setRally] = createSignal("");
   const [matchState, setMatchState] = createSignal<Stats>(initialStats);
+  const [history, setHistory] = createSignal<MatchHistory>(initialHistory);
+  const [invalid, setInvalid] = createSignal<InvalidEvent[]>([]);
+  const [editing, setEditing] = createSignal<number>();
   const [failReason, setFailReason] = createSignal<Reason>();
+  const [rulesPresets, setRulesPresets] = createSignal<RulesPreset[]>([]);
+  const [rulesPreset, setRulesPreset] = createSignal(0);
 
-  const parseRally = async () => {
-    const result = await invoke<ParseResult>(
-      "parse_rally",
-      { currentStats: matchState(), rally: rally() }
-    );
+  const setSnapshot = (snapshot: MatchSnapshot) => {
+    setHistory(snapshot.history);
+    setMatchState(snapshot.state);
+    setInvalid(snapshot.invalid);
+  }
+
+  // Every window gets notified about match updates, including the one that caused it
+  onMount(() => {
+    const unlisten = listen<MatchSnapshot>("match-updated", (e) => setSnapshot(e.payload));
+    onCleanup(() => unlisten.then((f) => f()));
 
+    invoke<RulesPreset[]>("get_rules_presets").then(setRulesPresets);
+
+    invoke<CommandResult<MatchSnapshot>>("get_match").then((result) => {
+      if (result.Ok !== undefined) {
+        setSnapshot(result.Ok);
+      }
+    });
+  });
+
+  const handleResult = (result: CommandResult<MatchSnapshot>) => {
     console.log(result);
 
     if (result.Ok !== undefined) {
-      setMatchState(result.Ok);
+      setEditing(undefined);
+      setRally("");
     }
     setFailReason(result.Fail);
   }
 
+  const parseRally = async () => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("parse_rally", { rally: rally() }));
+  }
+
+  const undo = async () => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("undo"));
+  }
+
+  const redo = async () => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("redo"));
+  }
+
+  const newMatch = async () => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>(
+      "new_match",
+      { rules: rulesPresets()[rulesPreset()].rules }
+    ));
+  }
+
+  const editRally = async (n: number) => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("edit_rally", { n, rally: rally() }));
+  }
+
+  const deleteEvent = async (n: number) => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("delete_event", { n }));
+  }
+
+  const setLineup = async (team: Team, input: string) => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>(
+      "set_lineup",
+      { team, positions: parsePlayers(input) }
+    ));
+  }
+
+  const substitute = async (team: Team, input: string, exceptional: boolean) => {
+    const [playerOut, playerIn] = parsePlayers(input);
+    handleResult(await invoke<CommandResult<MatchSnapshot>>(
+      "substitute",
+      { team, playerOut, playerIn, exceptional }
+    ));
+  }
+
+  const designateLibero = async (team: Team, input: string) => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>(
+      "designate_libero",
+      { team, libero: Number(input) }
+    ));
+  }
+
+  const swapLibero = async (team: Team, input: string) => {
+    const [libero, player] = parsePlayers(input);
+    handleResult(await invoke<CommandResult<MatchSnapshot>>(
+      "swap_libero",
+      { team, libero, player }
+    ));
+  }
+
+  const callTimeout = async (team: Team) => {
+    handleResult(await invoke<CommandResult<MatchSnapshot>>("call_timeout", { team }));
+  }
+
+  const timeoutsLeft = (team: Team) => {
+    const state = matchState();
+    const set = state.awayTeam.sets + state.homeTeam.sets + 1;
+    const taken = state.timeouts.filter((t) => t.team == team && t.moment.set == set).length;
+
+    return state.rules.timeoutsPerSet - taken;
+  }
+
+  const startEditing = (n: number) => {
+    const event = history().events[n];
+
+    if ("Rally" in event) {
+      setEditing(n);
+      setRally(event.Rally);
+    }
+  }
+
+  // Whether `moments` contains the current score, i.e. the last rally triggered it
+  const isDueNow = (moments: ScoreMoment[]) => {
+    const last = moments[moments.length - 1];
+    const state = matchState();
+
+    return last !== undefined
+      && last.set == state.awayTeam.sets + state.homeTeam.sets + 1
+      && last.awayPoints == state.awayTeam.points
+      && last.homePoints == state.homeTeam.points;
+  }
+
+  const invalidReason = (n: number) => invalid().find((i) => i.index == n)?.reason;
+
   const handleSubmit = (e: KeyboardEvent) => {
     if (e.key === "Enter") {
-      parseRally()
+      const n = editing();
+      n === undefined ? parseRally() : editRally(n);
     }
   }
 
@@ -86,19 +387,136 @@ function App() {
       <div class="flex flex-row gap-4">
         <span class="rounded p-1 text-xl bg-gray-900 text-white">{matchState().homeTeam.sets}</span>
         <span class="rounded p-1 text-xl bg-red-600 text-white">{matchState().homeTeam.points}</span>
+        <span class="p-1 text-xl">{matchState().servingTeam == "Home" ? "●" : ""}</span>
         <span class="rounded p-1 text-xl">-</span>
+        <span class="p-1 text-xl">{matchState().servingTeam == "Away" ? "●" : ""}</span>
         <span class="rounded p-1 text-xl bg-red-600 text-white">{matchState().awayTeam.points}</span>
         <span class="rounded p-1 text-xl bg-gray-900 text-white">{matchState().awayTeam.sets}</span>
       </div>
+      <Show when={matchState().setScores.length > 0}>
+        <p>{matchState().setScores.map(describeSetScore).join(", ")}</p>
+      </Show>
       <TextFieldRoot
         value={rally()}
         onChange={setRally}
-        disabled={matchState().status == "Finished"}
+        disabled={matchState().status == "Finished" && editing() === undefined}
         onKeyPress={handleSubmit}
         class="w-3/6"
       >
         <TextField />
       </TextFieldRoot>
+      <div class="flex flex-row gap-4">
+        <button
+          class="rounded p-1 bg-gray-900 text-white disabled:opacity-50"
+          disabled={history().events.length == 0}
+          onClick={undo}
+        >
+          Undo
+        </button>
+        <button
+          class="rounded p-1 bg-gray-900 text-white disabled:opacity-50"
+          disabled={history().undone.length == 0}
+          onClick={redo}
+        >
+          Redo
+        </button>
+        <select
+          class="rounded p-1"
+          value={rulesPreset()}
+          onChange={(e) => setRulesPreset(Number(e.currentTarget.value))}
+        >
+          <For each={rulesPresets()}>
+            {(preset, i) => <option value={i()}>{preset.name}</option>}
+          </For>
+        </select>
+        <button class="rounded p-1 bg-gray-900 text-white" onClick={newMatch}>
+          New match
+        </button>
+      </div>
+      <div class="flex flex-row gap-8">
+        <For each={["Home", "Away"] as Team[]}>
+          {(team) => {
+            const [lineup, setLineupInput] = createSignal("");
+            const [sub, setSub] = createSignal("");
+            const [injury, setInjury] = createSignal(false);
+            const [libero, setLibero] = createSignal("");
+            const [liberoSwap, setLiberoSwap] = createSignal("");
+
+            return (
+              <div class="flex flex-col gap-1">
+                <span>{team}</span>
+                <button
+                  class="rounded p-1 bg-gray-900 text-white disabled:opacity-50"
+                  disabled={timeoutsLeft(team) <= 0}
+                  onClick={() => callTimeout(team)}
+                >
+                  Timeout ({timeoutsLeft(team)} left)
+                </button>
+                <input
+                  class="rounded border p-1"
+                  placeholder="Lineup, e.g. 1 7 12 4 9 3"
+                  value={lineup()}
+                  onInput={(e) => setLineupInput(e.currentTarget.value)}
+                  onKeyPress={(e) => e.key === "Enter" && setLineup(team, lineup())}
+                />
+                <input
+                  class="rounded border p-1"
+                  placeholder="Substitution, e.g. 7 15"
+                  value={sub()}
+                  onInput={(e) => setSub(e.currentTarget.value)}
+                  onKeyPress={(e) => e.key === "Enter" && substitute(team, sub(), injury())}
+                />
+                <label class="text-sm">
+                  <input
+                    type="checkbox"
+                    checked={injury()}
+                    onChange={(e) => setInjury(e.currentTarget.checked)}
+                  />
+                  {" "}Injury (exceptional substitution)
+                </label>
+                <input
+                  class="rounded border p-1"
+                  placeholder="Libero, e.g. 10"
+                  value={libero()}
+                  onInput={(e) => setLibero(e.currentTarget.value)}
+                  onKeyPress={(e) => e.key === "Enter" && designateLibero(team, libero())}
+                />
+                <input
+                  class="rounded border p-1"
+                  placeholder="Libero swap, e.g. 10 5"
+                  value={liberoSwap()}
+                  onInput={(e) => setLiberoSwap(e.currentTarget.value)}
+                  onKeyPress={(e) => e.key === "Enter" && swapLibero(team, liberoSwap())}
+                />
+              </div>
+            );
+          }}
+        </For>
+      </div>
+      <ol class="w-3/6 flex flex-col gap-1">
+        <For each={history().events}>
+          {(event, n) => (
+            <li class="flex flex-row gap-2 items-center">
+              <span class={invalidReason(n()) ? "text-destructive" : ""}>{describeEvent(event)}</span>
+              <Show when={invalidReason(n())}>
+                {(r) => <span class="text-sm text-destructive">{r().errorMsg}</span>}
+              </Show>
+              <span class="ml-auto flex flex-row gap-2">
+                <Show when={"Rally" in event}>
+                  <button class="text-sm underline" onClick={() => startEditing(n())}>Edit</button>
+                </Show>
+                <button class="text-sm underline" onClick={() => deleteEvent(n())}>Delete</button>
+              </span>
+            </li>
+          )}
+        </For>
+      </ol>
+      <Show when={isDueNow(matchState().sideSwitches)}>
+        <p class="text-blue-900">SWITCH SIDES</p>
+      </Show>
+      <Show when={isDueNow(matchState().technicalTimeouts)}>
+        <p class="text-blue-900">TECHNICAL TIMEOUT</p>
+      </Show>
       <Show when={matchState().status == "Finished"}>
         <p class="text-green-900">MATCH FINISHED</p>
       </Show>